@@ -6,7 +6,6 @@ use crate::git::GitModulesInfo;
 use crate::git::GitPath;
 use crate::git::TreeId;
 use crate::git::git_command;
-use crate::git::git_global_command;
 use crate::git_fast_export_import::ChangedFile;
 use crate::git_fast_export_import::FastImportCommit;
 use crate::git_fast_export_import::ImportCommitRef;
@@ -37,6 +36,8 @@ use std::hash::Hash;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 #[derive(Debug)]
 pub struct TopRepo {
@@ -47,78 +48,90 @@ pub struct TopRepo {
 
 impl TopRepo {
     pub fn create(directory: PathBuf, url: gix::url::Url) -> Result<TopRepo> {
-        git_global_command()
-            .arg("init")
-            .arg("--quiet")
-            .arg(directory.as_os_str())
-            .safe_status()?
-            .check_success()
-            .context("Failed to initialize git repository")?;
-        git_command(&directory)
-            .args([
-                "config",
-                "remote.origin.pushUrl",
-                "https://ERROR.invalid/Please use 'git toprepo push ...' instead",
-            ])
-            .safe_status()?
-            .check_success()
-            .context("Failed to set git-config remote.origin.pushUrl")?;
-        git_command(&directory)
-            .args(["config", "remote.origin.url", &url.to_string()])
-            .safe_status()?
-            .check_success()
-            .context("Failed to set git-config remote.origin.url")?;
+        Self::create_impl(directory, url, false)
+    }
+
+    /// Configures a fresh toprepo exactly like [`Self::create`], then
+    /// immediately performs the initial `refs/namespaces/top/*` fetch as
+    /// part of the same call, matching how gitoxide's own `clone` module
+    /// prepares a repository and fetches in one flow.
+    pub fn clone(directory: PathBuf, url: gix::url::Url) -> Result<TopRepo> {
+        Self::create_impl(directory, url, true)
+    }
+
+    fn create_impl(directory: PathBuf, url: gix::url::Url, fetch: bool) -> Result<TopRepo> {
+        let gix_repo =
+            gix::init(&directory).context("Failed to initialize git repository")?;
         let toprepo_ref_prefix: String = RepoName::Top.to_ref_prefix();
-        git_command(&directory)
-            .args([
-                "config",
-                "--replace-all",
-                "remote.origin.fetch",
-                &format!("+refs/heads/*:{toprepo_ref_prefix}refs/heads/*"),
-            ])
-            .safe_status()?
-            .check_success()
-            .context("Failed to set git-config remote.origin.fetch (heads)")?;
-        git_command(&directory)
-            .args([
-                "config",
-                "--add",
-                "remote.origin.fetch",
-                &format!("+refs/tags/*:{toprepo_ref_prefix}refs/tags/*"),
-            ])
-            .safe_status()?
-            .check_success()
+
+        // Write every git-config key in a single edited snapshot instead of
+        // spawning a `git config` process per key.
+        let mut config = gix_repo.config_snapshot_mut();
+        let set_key = |config: &mut gix::config::SnapshotMut<'_>, key: &str, value: &str| {
+            config
+                .set_raw_value(&key, value)
+                .with_context(|| format!("Failed to set git-config {key}"))
+        };
+        set_key(
+            &mut config,
+            "remote.origin.pushUrl",
+            "https://ERROR.invalid/Please use 'git toprepo push ...' instead",
+        )?;
+        set_key(&mut config, "remote.origin.url", &url.to_string())?;
+        set_key(
+            &mut config,
+            "remote.origin.fetch",
+            &format!("+refs/heads/*:{toprepo_ref_prefix}refs/heads/*"),
+        )?;
+        config
+            .add_raw_value(
+                &"remote.origin.fetch",
+                format!("+refs/tags/*:{toprepo_ref_prefix}refs/tags/*").as_str(),
+            )
             .context("Failed to set git-config remote.origin.fetch (tags)")?;
-        git_command(&directory)
-            .args([
-                "config",
-                "--add",
-                "remote.origin.fetch",
-                &format!("+HEAD:{toprepo_ref_prefix}HEAD"),
-            ])
-            .safe_status()?
-            .check_success()
+        config
+            .add_raw_value(
+                &"remote.origin.fetch",
+                format!("+HEAD:{toprepo_ref_prefix}HEAD").as_str(),
+            )
             .context("Failed to set git-config remote.origin.fetch (HEAD)")?;
-        git_command(&directory)
-            .args(["config", "remote.origin.tagOpt", "--no-tags"])
-            .safe_status()?
-            .check_success()
-            .context("Failed to set git-config remote.origin.tagOpt")?;
-        git_command(&directory)
-            .args([
-                "config",
-                "toprepo.config",
-                "repo:refs/remotes/origin/HEAD:.gittoprepo.toml",
-            ])
-            .safe_status()?
-            .check_success()
-            .context("Failed to set git-config remote.origin.url")?;
-        git_command(&directory)
-            .args(["symbolic-ref", "HEAD", "refs/remotes/origin/HEAD"])
-            .safe_status()?
-            .check_success()
+        set_key(&mut config, "remote.origin.tagOpt", "--no-tags")?;
+        set_key(
+            &mut config,
+            "toprepo.config",
+            "repo:refs/remotes/origin/HEAD:.gittoprepo.toml",
+        )?;
+        config
+            .commit()
+            .context("Failed to write the git-config for the new toprepo")?;
+
+        // Point HEAD at the namespaced origin HEAD via a single ref
+        // transaction, instead of shelling out to `git symbolic-ref`.
+        gix_repo
+            .edit_reference(gix::refs::transaction::RefEdit {
+                change: gix::refs::transaction::Change::Update {
+                    log: gix::refs::transaction::LogChange {
+                        mode: gix::refs::transaction::RefLog::AndReference,
+                        force_create_reflog: false,
+                        message: b"git-toprepo create".into(),
+                    },
+                    expected: gix::refs::transaction::PreviousValue::Any,
+                    new: gix::refs::Target::Symbolic(
+                        "refs/remotes/origin/HEAD"
+                            .try_into()
+                            .expect("valid ref name"),
+                    ),
+                },
+                name: "HEAD".try_into().expect("valid ref name"),
+                deref: false,
+            })
             .context("Failed to reset HEAD")?;
-        Self::open(directory)
+
+        let toprepo = Self::open(directory)?;
+        if fetch {
+            toprepo.fetch_toprepo_quiet()?;
+        }
+        Ok(toprepo)
     }
 
     pub fn open(directory: PathBuf) -> Result<TopRepo> {
@@ -131,34 +144,128 @@ impl TopRepo {
         })
     }
 
-    pub fn fetch_toprepo(&self) -> Result<()> {
-        git_command(&self.directory)
-            .arg("fetch")
-            .arg("--recurse-submodules=false")
-            .safe_status()?
-            .check_success()?;
-        Ok(())
+    pub fn fetch_toprepo(&self) -> Result<FetchOutcome> {
+        self.fetch_toprepo_with_progress(false, indicatif::MultiProgress::new())
     }
 
-    pub fn fetch_toprepo_quiet(&self) -> Result<()> {
-        git_command(&self.directory)
-            .arg("fetch")
-            .arg("--recurse-submodules=false")
-            .arg("--quiet")
-            .safe_status()?
-            .check_success()?;
-        Ok(())
+    pub fn fetch_toprepo_quiet(&self) -> Result<FetchOutcome> {
+        self.fetch_toprepo_with_progress(false, indicatif::MultiProgress::with_draw_target(
+            indicatif::ProgressDrawTarget::hidden(),
+        ))
+    }
+
+    /// Fetches `refs/namespaces/top/*` from `remote.origin` directly through
+    /// gix, without shelling out to `git fetch`. When `dry_run` is true the
+    /// ref edits and their expected updates are computed but never applied,
+    /// mirroring gix's own dry-run fetch path.
+    pub fn fetch_toprepo_with_progress(
+        &self,
+        dry_run: bool,
+        progress: indicatif::MultiProgress,
+    ) -> Result<FetchOutcome> {
+        let repo = self.gix_repo.to_thread_local();
+        let remote = repo
+            .find_remote("origin")
+            .context("Failed to find the 'origin' remote")?;
+        let pb = progress.add(
+            indicatif::ProgressBar::no_length()
+                .with_style(
+                    indicatif::ProgressStyle::default_spinner()
+                        .template("{elapsed:>4} {msg} {pos}")
+                        .unwrap(),
+                )
+                .with_message("Fetching refs/namespaces/top/*"),
+        );
+        let connection = remote
+            .connect(gix::remote::Direction::Fetch)
+            .context("Failed to connect to the 'origin' remote")?;
+        let fetch_progress = IndicatifProgress::new(pb.clone());
+        let outcome = connection
+            .prepare_fetch(fetch_progress.clone(), Default::default())
+            .context("Failed to prepare fetch")?
+            .with_dry_run(dry_run)
+            .receive(fetch_progress, &gix::interrupt::IS_INTERRUPTED)
+            .context("Failed to fetch from 'origin'")?;
+        pb.finish_and_clear();
+
+        let updates = outcome
+            .ref_updates
+            .unwrap_or_default()
+            .into_iter()
+            .map(|update| RefUpdate {
+                name: update.name,
+                mode: RefUpdateMode::from_gix(&update.mode),
+                old: update.edit.as_ref().and_then(|edit| match &edit.change {
+                    gix::refs::transaction::Change::Update { expected, .. } => match expected {
+                        gix::refs::transaction::PreviousValue::MustExistAndMatch(
+                            gix::refs::Target::Object(id),
+                        ) => Some(CommitId::from(*id)),
+                        _ => None,
+                    },
+                    _ => None,
+                }),
+                new: update.edit.as_ref().and_then(|edit| match &edit.change {
+                    gix::refs::transaction::Change::Update {
+                        new: gix::refs::Target::Object(id),
+                        ..
+                    } => Some(CommitId::from(*id)),
+                    _ => None,
+                }),
+            })
+            .collect();
+        Ok(FetchOutcome { updates })
+    }
+
+    /// Self-healing half of the on-disk cache contract: if the caller loaded
+    /// `storage` from disk and passes along the [`CacheFrameHash`] that was
+    /// stored next to it, this re-verifies `storage` still matches that
+    /// frame ([`TopRepoCache::verify_framed_content_hash`]) before either
+    /// hot path below relies on it, and discards it back to
+    /// [`TopRepoCache::default`] on a mismatch so the rest of the function
+    /// rebuilds from the repos instead of expanding on top of a stale or
+    /// corrupted cache. A `None` frame (no prior cache, e.g. first run)
+    /// skips the check.
+    fn discard_storage_if_stale(
+        storage: &mut TopRepoCache,
+        expected_cache_frame: Option<CacheFrameHash>,
+        logger: &Logger,
+    ) {
+        let Some(expected) = expected_cache_frame else {
+            return;
+        };
+        if !storage.verify_framed_content_hash(expected) {
+            logger.warning(
+                "Cached TopRepoCache is stale or from an incompatible schema version; \
+                 discarding it and rebuilding from the repository instead of trusting it"
+                    .to_string(),
+            );
+            *storage = TopRepoCache::default();
+        }
     }
 
     pub fn refilter(
         &self,
         storage: &mut TopRepoCache,
+        expected_cache_frame: Option<CacheFrameHash>,
         config: &crate::config::GitTopRepoConfig,
         logger: Logger,
         progress: indicatif::MultiProgress,
     ) -> Result<()> {
+        Self::discard_storage_if_stale(storage, expected_cache_frame, &logger);
         let repo = self.gix_repo.to_thread_local();
 
+        // Every repo this toprepo knows about - the top repo and every
+        // submodule already expanded into `storage.repos` - is fetched into
+        // this same git dir, just under its own `refs/namespaces/<name>/*`
+        // prefix, so their tags can all be read through this one `repo`
+        // handle rather than needing a per-submodule repository like the
+        // expander's own fetch/expand machinery does.
+        for (repo_name, repo_data) in storage.repos.iter_mut() {
+            repo_data
+                .load_tags(&repo, &repo_name.to_ref_prefix())
+                .with_context(|| format!("Failed to load tags for {repo_name:?}"))?;
+        }
+
         let old_origin_refs = repo
             .references()?
             .prefixed(b"refs/remotes/origin/".as_bstr())?
@@ -214,11 +321,12 @@ impl TopRepo {
                 }
             }
         }
-        let mut unknown_toprepo_tips = toprepo_object_tip_ids
+        let unknown_toprepo_tip_ids: Vec<CommitId> = toprepo_object_tip_ids
             .into_iter()
             .filter(|commit_id| !storage.top_to_mono_map.contains_key(commit_id))
-            .peekable();
-        if unknown_toprepo_tips.peek().is_some() {
+            .map(|commit_id| commit_id.into_inner())
+            .collect();
+        if !unknown_toprepo_tip_ids.is_empty() {
             let progress = progress.clone();
             let pb = progress.add(
                 indicatif::ProgressBar::no_length()
@@ -231,7 +339,7 @@ impl TopRepo {
             );
             let (stop_commits, num_commits_to_export) = crate::git::get_first_known_commits(
                 &repo,
-                unknown_toprepo_tips.map(|commit_id| commit_id.into_inner()),
+                unknown_toprepo_tip_ids.iter().copied(),
                 |commit_id| {
                     storage
                         .top_to_mono_map
@@ -242,6 +350,15 @@ impl TopRepo {
             drop(pb);
 
             println!("Found {num_commits_to_export} commits to expand");
+            let mut submodule_guard = SubmoduleExpansionGuard::new(config.max_submodule_depth());
+            self.prefetch_missing_submodule_commits(
+                &repo,
+                storage,
+                &unknown_toprepo_tip_ids,
+                &logger,
+                progress.clone(),
+                &mut submodule_guard,
+            )?;
             let fast_importer = crate::git_fast_export_import::FastImportRepo::new(
                 self.gix_repo.git_dir(),
                 logger.clone(),
@@ -256,6 +373,7 @@ impl TopRepo {
                 imported_commits: HashMap::new(),
                 bumps: crate::expander::BumpCache::default(),
                 inject_at_oldest_super_commit: false,
+                submodule_guard,
             };
 
             expander.expand_toprepo_commits(
@@ -265,25 +383,43 @@ impl TopRepo {
             )?;
             expander.wait()?;
 
+            self.enforce_signature_policy(storage, config, &logger)?;
+
             Self::update_refs(
                 &repo,
                 &logger,
                 toprepo_symbolic_tips,
                 old_origin_refs,
                 new_origin_ref_names,
+                RefUpdateSafety::AllowUnguardedDeletes,
+                false,
             )?;
         }
         Ok(())
     }
 
+    /// Computes (and, unless `dry_run`, applies) the `refs/remotes/origin/*`
+    /// ref edits implied by the current `refs/namespaces/top/*` tips.
+    ///
+    /// With `safety == RejectUnguardedDeletes`, a ref that disappeared
+    /// upstream is not deleted with just a warning: the call fails before
+    /// any ref is edited. No expected prior value can be passed in, so in
+    /// this mode every such deletion is rejected; gix's
+    /// `PreviousValue::MustExistAndMatch` on the applied edits only protects
+    /// against a ref moving concurrently. The same gate applies to an
+    /// existing symbolic ref (e.g. `HEAD`) being repointed at a different
+    /// target.
     fn update_refs(
         repo: &gix::Repository,
         logger: &Logger,
         toprepo_symbolic_tips: Vec<(FullName, FullName)>,
         old_origin_refs: HashMap<FullName, gix::refs::Reference>,
         new_origin_ref_names: HashSet<FullName>,
-    ) -> Result<()> {
+        safety: RefUpdateSafety,
+        dry_run: bool,
+    ) -> Result<RefUpdatePlan> {
         let mut ref_edits = Vec::new();
+        let mut plan = RefUpdatePlan::default();
         // Update symbolic refs/remotes/origin/* if needed.
         for (top_link_name, top_target_name) in &toprepo_symbolic_tips {
             let origin_link_name =
@@ -301,6 +437,23 @@ impl TopRepo {
             let new_target = gix::refs::Target::Symbolic(origin_target_name);
             let old_target = old_origin_refs.get(&origin_link_name).map(|r| &r.target);
             if old_target != Some(&new_target) {
+                if old_target.is_some() && safety == RefUpdateSafety::RejectUnguardedDeletes {
+                    anyhow::bail!(
+                        "Refusing to repoint symbolic ref {} that already exists \
+                        while unguarded changes are rejected",
+                        origin_link_name.as_bstr()
+                    );
+                }
+                match old_target {
+                    Some(old_target) => plan.updated.push((
+                        origin_link_name.clone(),
+                        old_target.clone(),
+                        new_target.clone(),
+                    )),
+                    None => plan
+                        .created
+                        .push((origin_link_name.clone(), new_target.clone())),
+                }
                 ref_edits.push(gix::refs::transaction::RefEdit {
                     change: gix::refs::transaction::Change::Update {
                         log: gix::refs::transaction::LogChange {
@@ -324,10 +477,19 @@ impl TopRepo {
             if new_origin_ref_names.contains(&old_ref.name) {
                 continue;
             }
+            if safety == RefUpdateSafety::RejectUnguardedDeletes {
+                anyhow::bail!(
+                    "Refusing to delete {} that disappeared upstream \
+                    while unguarded deletions are rejected",
+                    old_ref.name.as_bstr()
+                );
+            }
             logger.warning(format!(
                 "Deleting now removed ref {}",
                 old_ref.name.as_bstr()
             ));
+            plan.deleted
+                .push((old_ref.name.clone(), old_ref.target.clone()));
             ref_edits.push(gix::refs::transaction::RefEdit {
                 change: gix::refs::transaction::Change::Delete {
                     expected: gix::refs::transaction::PreviousValue::MustExistAndMatch(
@@ -339,24 +501,27 @@ impl TopRepo {
                 deref: false,
             });
         }
-        // Apply the ref changes.
-        if !ref_edits.is_empty() {
+        // Apply the ref changes, unless this is only a preview.
+        if !dry_run && !ref_edits.is_empty() {
             repo.edit_references(ref_edits)
                 .context("Failed to update all the refs/remotes/origin/* references")?;
         }
-        Ok(())
+        Ok(plan)
     }
 
     pub fn expand_toprepo_refs(
         &self,
         refs: &Vec<FullName>,
         storage: &mut TopRepoCache,
+        expected_cache_frame: Option<CacheFrameHash>,
         config: &crate::config::GitTopRepoConfig,
         logger: Logger,
         progress: indicatif::MultiProgress,
     ) -> Result<()> {
+        Self::discard_storage_if_stale(storage, expected_cache_frame, &logger);
         let repo = self.gix_repo.to_thread_local();
 
+        let mut toprepo_symbolic_tips = Vec::new();
         let mut toprepo_object_tip_names = Vec::new();
         let mut toprepo_object_tip_ids = Vec::new();
         for full_ref in refs {
@@ -385,9 +550,14 @@ impl TopRepo {
             let r = r.detach();
             match r.target {
                 gix::refs::Target::Symbolic(target_name) => {
-                    unimplemented!(
-                        "symbolic refs in expand_toprepo_refs are not supported yet: {target_name}"
-                    );
+                    toprepo_symbolic_tips.push((r.name, target_name.clone()));
+                    // Enqueue the underlying object tip for expansion, once,
+                    // under its own name so the symbolic link can be
+                    // recreated on the output side afterwards.
+                    if !toprepo_object_tip_names.contains(&target_name) {
+                        toprepo_object_tip_names.push(target_name);
+                        toprepo_object_tip_ids.push(TopRepoCommitId(r_target.detach().id));
+                    }
                 }
                 gix::refs::Target::Object(object_id) => {
                     toprepo_object_tip_names.push(r.name);
@@ -409,6 +579,7 @@ impl TopRepo {
             .iter()
             .map(|commit_id| **commit_id)
             .collect::<HashSet<_>>();
+        let new_tips: Vec<CommitId> = toprepo_object_tip_ids_set.iter().copied().collect();
         let (stop_commits, num_commits_to_export) = crate::git::get_first_known_commits(
             &repo,
             toprepo_object_tip_ids
@@ -425,6 +596,15 @@ impl TopRepo {
         drop(pb);
 
         println!("Found {num_commits_to_export} commits to expand");
+        let mut submodule_guard = SubmoduleExpansionGuard::new(config.max_submodule_depth());
+        self.prefetch_missing_submodule_commits(
+            &repo,
+            storage,
+            &new_tips,
+            &logger,
+            progress.clone(),
+            &mut submodule_guard,
+        )?;
         let fast_importer = crate::git_fast_export_import::FastImportRepo::new(
             self.gix_repo.git_dir(),
             logger.clone(),
@@ -439,6 +619,7 @@ impl TopRepo {
             imported_commits: HashMap::new(),
             bumps: crate::expander::BumpCache::default(),
             inject_at_oldest_super_commit: false,
+            submodule_guard,
         };
 
         expander.expand_toprepo_commits(
@@ -447,6 +628,41 @@ impl TopRepo {
             num_commits_to_export,
         )?;
         expander.wait()?;
+
+        // Recreate the symbolic links on the output side, now that their
+        // underlying object tips have been expanded.
+        let mut ref_edits = Vec::new();
+        for (top_link_name, top_target_name) in &toprepo_symbolic_tips {
+            let output_link_name =
+                TopRepoExpander::input_ref_to_output_ref(top_link_name.borrow())?;
+            let Ok(output_target_name) =
+                TopRepoExpander::input_ref_to_output_ref(top_target_name.borrow())
+            else {
+                logger.warning(format!(
+                    "Skipping symbolic ref {} that points outside the top repo, to {}.",
+                    top_link_name.as_bstr(),
+                    top_target_name.as_bstr(),
+                ));
+                continue;
+            };
+            ref_edits.push(gix::refs::transaction::RefEdit {
+                change: gix::refs::transaction::Change::Update {
+                    log: gix::refs::transaction::LogChange {
+                        mode: gix::refs::transaction::RefLog::AndReference,
+                        force_create_reflog: false,
+                        message: b"git-toprepo expand".into(),
+                    },
+                    expected: gix::refs::transaction::PreviousValue::Any,
+                    new: gix::refs::Target::Symbolic(output_target_name),
+                },
+                name: output_link_name,
+                deref: false,
+            });
+        }
+        if !ref_edits.is_empty() {
+            repo.edit_references(ref_edits)
+                .context("Failed to recreate symbolic refs on the output side")?;
+        }
         Ok(())
     }
 
@@ -478,6 +694,10 @@ impl TopRepo {
                 )
             })?
             .clone(); // Clone to avoid borrowing the `storage` object.
+        // A trivial submodule-bump merge carries no tree change of its own,
+        // so there is nothing to gain from expanding it: collapse straight
+        // to the parent it is equivalent to.
+        let thin_commit_to_inject = thin_commit_to_inject.collapse_trivial_merges();
 
         let pb = progress.add(
             indicatif::ProgressBar::no_length()
@@ -507,6 +727,12 @@ impl TopRepo {
                 &pb,
             )?;
         drop(pb);
+        let possible_mono_parents = Self::resolve_submodule_regressions(
+            possible_mono_parents,
+            abs_sub_path,
+            storage,
+            &thin_commit_to_inject,
+        );
 
         let fast_importer = crate::git_fast_export_import::FastImportRepo::new(
             self.gix_repo.git_dir(),
@@ -522,6 +748,7 @@ impl TopRepo {
             imported_commits: HashMap::new(),
             bumps: crate::expander::BumpCache::default(),
             inject_at_oldest_super_commit: true,
+            submodule_guard: SubmoduleExpansionGuard::new(config.max_submodule_depth()),
         };
         let result = (|| {
             let Some(_mono_commit) = expander.inject_submodule_commit(
@@ -544,6 +771,63 @@ impl TopRepo {
         result
     }
 
+    /// For each candidate mono parent that already has a bump for `path`,
+    /// checks whether `new_tip` regresses it ([`ThinCommit::is_regression`])
+    /// and, if so, replaces that parent with a synthesized "Resetting to X"
+    /// mono commit ([`MonoRepoCommit::new_reset_rc`]) forcing `path` back to
+    /// `new_tip` first, so the forward history expands on top of the reset
+    /// point instead of creating a merge line across the regression.
+    ///
+    /// Both `old_tip` and `new_tip` are passed through
+    /// [`ThinCommit::collapse_trivial_merges`] first, so a regression
+    /// decision is never made against a merge that expansion would collapse
+    /// away anyway.
+    ///
+    /// PARTIAL: this only covers the single mono parent being injected onto
+    /// here; a regression detected deeper in history during a full
+    /// refilter/expand still falls back to
+    /// [`ExpandedSubmodule::RegressedNotFullyImplemented`], because that
+    /// detection would have to live in
+    /// `get_recursive_submodule_bumps`/`expand_inner_submodules` in the
+    /// expander, which this request does not touch.
+    fn resolve_submodule_regressions(
+        mono_parents: Vec<Rc<MonoRepoCommit>>,
+        path: &GitPath,
+        storage: &TopRepoCache,
+        new_tip: &Rc<ThinCommit>,
+    ) -> Vec<Rc<MonoRepoCommit>> {
+        mono_parents
+            .into_iter()
+            .map(|mono_parent| {
+                let Some(ExpandedOrRemovedSubmodule::Expanded(ExpandedSubmodule::Expanded(
+                    content,
+                ))) = mono_parent.submodule_bumps.get(path)
+                else {
+                    return mono_parent;
+                };
+                let Some(old_tip) = storage
+                    .repos
+                    .get(&RepoName::SubRepo(content.repo_name.clone()))
+                    .and_then(|repo_data| repo_data.thin_commits.get(&content.orig_commit_id))
+                else {
+                    return mono_parent;
+                };
+                let old_tip = old_tip.collapse_trivial_merges();
+                if !old_tip.is_regression(new_tip) {
+                    return mono_parent;
+                }
+                MonoRepoCommit::new_reset_rc(
+                    mono_parent,
+                    path.clone(),
+                    SubmoduleContent {
+                        repo_name: content.repo_name.clone(),
+                        orig_commit_id: new_tip.commit_id,
+                    },
+                )
+            })
+            .collect()
+    }
+
     #[allow(unused_variables)]
     #[allow(clippy::too_many_arguments)]
     pub fn push(
@@ -607,6 +891,7 @@ impl TopRepo {
         let mut bumps = BumpCache::default();
         let mut imported_mono_commits = HashMap::new();
         let mut imported_submod_commits = HashMap::new();
+        let mut committer_times: HashMap<CommitId, i64> = HashMap::new();
         for entry in fast_exporter {
             let entry = entry?; // TODO: error handling
             match entry {
@@ -614,6 +899,10 @@ impl TopRepo {
                     // TODO: Should we check if exported_mono_commit.original_id exists in the top_repo_cache?
                     let mono_commit_id = MonoRepoCommitId::new(exported_mono_commit.original_id);
                     let gix_mono_commit = repo.find_commit(*mono_commit_id)?;
+                    committer_times.insert(
+                        exported_mono_commit.original_id,
+                        exported_mono_commit.committer_info.time.seconds,
+                    );
                     let mono_parents = exported_mono_commit
                         .parents
                         .iter()
@@ -640,10 +929,14 @@ impl TopRepo {
                         BTreeMap::new();
                     for fc in exported_mono_commit.file_changes {
                         let (repo_name, submod_path, rel_path, push_url) = Self::resolve_push_repo(
+                            &repo,
+                            top_repo_cache,
                             &gix_mono_commit,
+                            mono_parents.first(),
                             GitPath::new(fc.path),
                             top_push_url.clone(),
                             config,
+                            &logger,
                         )?;
                         grouped_file_changes
                             .entry((submod_path, repo_name, push_url))
@@ -653,7 +946,7 @@ impl TopRepo {
                                 change: fc.change,
                             });
                     }
-                    let (message, topic) =
+                    let (message, topic, explicit_change_id) =
                         Self::rewrite_push_message(exported_mono_commit.message.to_str()?);
                     if grouped_file_changes.len() > 1 && topic.is_none() {
                         anyhow::bail!(
@@ -661,6 +954,8 @@ impl TopRepo {
                             Please amend the commit message to add a 'Topic: something-descriptive' line."
                         );
                     }
+                    let mono_tree_id: TreeId = gix_mono_commit.tree_id()?.detach();
+                    let author_for_change_id = format!("{:?}", exported_mono_commit.author_info);
                     for ((abs_sub_path, repo_name, push_url), file_changes) in grouped_file_changes
                     {
                         let push_branch = format!("{}push", repo_name.to_ref_prefix());
@@ -695,13 +990,52 @@ impl TopRepo {
                                 ),
                             }
                         }
+                        // Reuse the same Change-Id across an unmodified
+                        // re-push of this exact mono commit, so Gerrit (or
+                        // similar) updates the existing review instead of
+                        // creating a new one. Each submodule gets its own
+                        // Change-Id since it is pushed to its own review,
+                        // keyed by push URL so a commit split across
+                        // several submodules still gets one stable id per
+                        // target. Keyed by the parent submodule commits
+                        // rather than `mono_commit_id` itself (see
+                        // `TopRepoCache::change_ids`'s doc comment) so that
+                        // re-running after a plain amend of the tip, which
+                        // changes `mono_commit_id` but not its parents,
+                        // reuses it too; a Change-Id trailer already present
+                        // in the message (`explicit_change_id` above) still
+                        // takes priority and is the only thing that survives
+                        // a rebase that also changes the parents.
+                        let change_id = match &explicit_change_id {
+                            Some(change_id) => change_id.clone(),
+                            None => top_repo_cache
+                                .change_ids
+                                .entry((
+                                    push_url.to_bstring().to_string(),
+                                    parents_commit_ids.clone(),
+                                ))
+                                .or_insert_with(|| {
+                                    Self::generate_change_id(
+                                        &mono_tree_id,
+                                        &author_for_change_id,
+                                        &message,
+                                    )
+                                })
+                                .clone(),
+                        };
+                        let mut message_with_change_id = message.clone();
+                        if !message_with_change_id.ends_with('\n') {
+                            message_with_change_id.push('\n');
+                        }
+                        message_with_change_id.push_str(&format!("Change-Id: {change_id}\n"));
+
                         let import_ref = fast_importer.write_commit(&FastImportCommit {
                             branch: <&FullNameRef as TryFrom<_>>::try_from(&push_branch)
                                 .expect("valid ref name"),
                             author_info: exported_mono_commit.author_info.clone(),
                             committer_info: exported_mono_commit.committer_info.clone(),
                             encoding: exported_mono_commit.encoding.clone(),
-                            message: bstr::BString::from(message.clone()),
+                            message: bstr::BString::from(message_with_change_id),
                             file_changes,
                             parents,
                             original_id: None,
@@ -741,6 +1075,7 @@ impl TopRepo {
                             topic.clone(),
                             import_commit_id,
                             parents_commit_ids,
+                            exported_mono_commit.original_id,
                         ));
                     }
                     pb.inc(1);
@@ -756,10 +1091,30 @@ impl TopRepo {
         fast_importer.wait()?;
         drop(pb);
 
+        // Order the pushes deterministically: parents strictly before
+        // children, ties broken by committer timestamp, so that pushing the
+        // same set of commits always produces the same push sequence
+        // regardless of the order fast-export happened to deliver them in.
+        let mono_commit_order =
+            topo_sort_mono_commits(&imported_mono_commits, |id| {
+                committer_times.get(id).copied().unwrap_or(0)
+            });
+        let mono_commit_position: HashMap<CommitId, usize> = mono_commit_order
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| (id, index))
+            .collect();
+        to_push_metadata.sort_by_key(|(_, _, _, _, mono_commit_id)| {
+            mono_commit_position
+                .get(mono_commit_id)
+                .copied()
+                .unwrap_or(usize::MAX)
+        });
+
         // Group the pushes together to run fewer git-push commands.
         to_push_metadata.reverse();
         let mut redundant_pushes = HashMap::new();
-        to_push_metadata.retain(|(push_url, topic, commit_id, parents)| {
+        to_push_metadata.retain(|(push_url, topic, commit_id, parents, _mono_commit_id)| {
             let is_needed = redundant_pushes
                 .remove(&(push_url.clone(), *commit_id))
                 .as_ref()
@@ -776,7 +1131,7 @@ impl TopRepo {
 
         progress.suspend(|| {
             let info_label = if dry_run { "Would run" } else { "Running" };
-            for (push_url, topic, commit_id, _parents) in &to_push_metadata {
+            for (push_url, topic, commit_id, _parents, _mono_commit_id) in &to_push_metadata {
                 let topic_arg = match topic {
                     Some(topic) => format!(" -o topic={topic}"),
                     None => String::new(),
@@ -789,7 +1144,7 @@ impl TopRepo {
         });
         if !dry_run {
             let mut failed_pushes = 0;
-            for (push_url, topic, commit_id, _parents) in to_push_metadata {
+            for (push_url, topic, commit_id, _parents, _mono_commit_id) in to_push_metadata {
                 let mut cmd = git_command(&self.directory);
                 cmd.arg("push").arg(push_url.to_bstring().to_os_str()?);
                 if let Some(topic) = topic {
@@ -811,12 +1166,15 @@ impl TopRepo {
         Ok(())
     }
 
-    fn rewrite_push_message(message: &str) -> (String, Option<String>) {
+    fn rewrite_push_message(message: &str) -> (String, Option<String>, Option<String>) {
         let mut filtered_message = String::with_capacity(message.len());
         let mut topic = None;
+        let mut change_id = None;
         for line in message.lines() {
             if let Some(topic_name) = line.strip_prefix("Topic: ") {
                 topic = Some(topic_name.to_owned());
+            } else if let Some(id) = line.strip_prefix("Change-Id: ") {
+                change_id = Some(id.to_owned());
             } else if line.starts_with("^-- ") {
                 // Ignore '^-- path/to/submod 0123...'
             } else {
@@ -824,15 +1182,36 @@ impl TopRepo {
                 filtered_message.push('\n');
             }
         }
-        (filtered_message, topic)
+        (filtered_message, topic, change_id)
+    }
+
+    /// Deterministically derives a Gerrit-style `Change-Id` from the tree,
+    /// author and original message of a mono commit, the same way Gerrit's
+    /// own commit-msg hook hashes a commit's content: a real 40-hex-digit
+    /// SHA-1 digest (via [`sha1_hex`]) over exactly that content, not a
+    /// general-purpose, zero-padded 64-bit hash. [`TopRepoCache::change_ids`]
+    /// is what makes the id survive an amend that does change the content:
+    /// it caches this function's output per parent lineage, not per commit
+    /// id, so a re-push after amending the tip looks up (and gets back) the
+    /// same cached id instead of hashing the new content into a new one. A
+    /// Change-Id trailer already present in the message always takes
+    /// priority over both.
+    fn generate_change_id(tree_id: &TreeId, author: &str, message: &str) -> String {
+        let content = format!("tree {tree_id}\nauthor {author}\n\n{message}");
+        format!("I{}", sha1_hex(content.as_bytes()))
     }
 
     /// Resolves which repository to push to. Note that the push URL might not be part of the git-toprepo configuration, so `url` is used when resolving the that.
+    #[allow(clippy::too_many_arguments)]
     fn resolve_push_repo(
+        repo: &gix::Repository,
+        storage: &TopRepoCache,
         mono_commit: &gix::Commit,
+        mono_parent: Option<&Rc<MonoRepoCommit>>,
         path: GitPath,
         mut push_url: gix::Url,
         config: &mut crate::config::GitTopRepoConfig,
+        logger: &Logger,
     ) -> Result<(RepoName, GitPath, GitPath, gix::Url)> {
         let mut repo_name = RepoName::Top;
         let mut repo_path = GitPath::new(b"".into());
@@ -860,8 +1239,69 @@ impl TopRepo {
             .with_context(|| format!("Failed to parse {dot_gitmodules_path} file"))?;
             let Some((submod_path, sub_url)) = git_modules_info.get_containing_submodule(&rel_path)
             else {
+                // The live tree has no `.gitmodules` stanza covering this
+                // path at all - it may still be a gitlink that was added
+                // without ever recording one. Fall back to the historical
+                // `.gitmodules` blob recorded for the enclosing repo's
+                // current content instead of silently treating it as plain
+                // content of the enclosing repo.
+                let rel_tree_path = repo_path.join(&rel_path);
+                let is_a_gitlink = matches!(
+                    mono_commit
+                        .tree()?
+                        .lookup_entry_by_path(rel_tree_path.to_path()?)?
+                        .map(|entry| entry.mode()),
+                    Some(mode) if mode.is_commit()
+                );
+                if is_a_gitlink {
+                    match Self::current_thin_commit_for_push(
+                        storage,
+                        mono_parent,
+                        &repo_name,
+                        &repo_path,
+                    ) {
+                        Some(thin_commit) => {
+                            if let Some(sub_url) =
+                                Self::resolve_submodule_url(repo, thin_commit, &rel_path, logger)?
+                            {
+                                let (sub_repo_name, _) = config.get_or_insert_from_url(&sub_url)?;
+                                return Ok((
+                                    RepoName::SubRepo(sub_repo_name),
+                                    rel_tree_path,
+                                    GitPath::new(b"".into()),
+                                    sub_url,
+                                ));
+                            }
+                        }
+                        None => {
+                            logger.warning(format!(
+                                "Could not resolve a URL for submodule {rel_tree_path}: \
+                                 no .gitmodules entry recorded for it and no prior \
+                                 commit is known to consult instead"
+                            ));
+                        }
+                    }
+                }
                 return Ok((repo_name, repo_path, rel_path.clone(), push_url));
             };
+            // A `.gitmodules` stanza can outlive the submodule it describes:
+            // a user may delete the stanza but leave the checked-out files
+            // in place as a plain directory, and until the stanza is also
+            // cleaned up the tree entry itself already stopped being a
+            // gitlink. In that case the path no longer resolves to a
+            // submodule at all, so attribute it to the enclosing repo
+            // instead of descending into a submodule that does not exist.
+            let submod_tree_path = repo_path.join(submod_path);
+            let is_still_a_submodule = matches!(
+                mono_commit
+                    .tree()?
+                    .lookup_entry_by_path(submod_tree_path.to_path()?)?
+                    .map(|entry| entry.mode()),
+                Some(mode) if mode.is_commit()
+            );
+            if !is_still_a_submodule {
+                return Ok((repo_name, repo_path, rel_path.clone(), push_url));
+            }
             // Apply one submodule level.
             rel_path = GitPath::new(
                 rel_path
@@ -883,6 +1323,707 @@ impl TopRepo {
             repo_name = RepoName::SubRepo(sub_repo_name);
         }
     }
+
+    /// Resolves the submodule URL configured for `path` at `thin_commit`,
+    /// even when the commit's tree has no `.gitmodules` file of its own:
+    /// the `.gitmodules` stanza is read straight from the blob recorded in
+    /// [`ThinCommit::dot_gitmodules`] (populated when the commit was first
+    /// loaded, so it is available here regardless of what the current tree
+    /// looks like). Returns `Ok(None)` together with a logged diagnostic
+    /// when the path cannot be resolved this way, rather than failing the
+    /// whole operation over one unresolved submodule.
+    ///
+    /// TODO: this only ever resolves from a recorded `.gitmodules` blob. A
+    /// path with no `.gitmodules` coverage anywhere in `thin_commit`'s
+    /// history (e.g. a gitlink checked in without ever adding a stanza for
+    /// it) still falls through to the warning below; it should instead fall
+    /// back to a configured path-to-URL mapping in `GitTopRepoConfig` once
+    /// that mapping exists there.
+    fn resolve_submodule_url(
+        repo: &gix::Repository,
+        thin_commit: &ThinCommit,
+        path: &GitPath,
+        logger: &Logger,
+    ) -> Result<Option<gix::Url>> {
+        if let Some(dot_gitmodules_id) = thin_commit.dot_gitmodules {
+            let dot_gitmodules_bytes = repo
+                .find_object(dot_gitmodules_id)
+                .with_context(|| format!("Failed to read .gitmodules blob {dot_gitmodules_id}"))?
+                .try_into_blob()
+                .with_context(|| format!(".gitmodules blob {dot_gitmodules_id} is not a blob"))?
+                .take_data();
+            let git_modules_info = GitModulesInfo::parse_dot_gitmodules_bytes(
+                &dot_gitmodules_bytes,
+                path.to_path()?.to_owned(),
+            )
+            .with_context(|| format!("Failed to parse .gitmodules blob {dot_gitmodules_id}"))?;
+            if let Some((_submod_path, sub_url)) = git_modules_info.get_containing_submodule(path)
+            {
+                let sub_url = match sub_url {
+                    Ok(sub_url) => sub_url,
+                    Err(err) => anyhow::bail!("{err:#}"),
+                };
+                return Ok(Some(EMPTY_GIX_URL.clone().join(sub_url)));
+            }
+        }
+        logger.warning(format!(
+            "Could not resolve a URL for submodule {path} at commit {}: \
+             no .gitmodules entry recorded for it",
+            thin_commit.commit_id.to_hex()
+        ));
+        Ok(None)
+    }
+
+    /// Finds the [`ThinCommit`] that currently backs `repo_name`'s content
+    /// at `repo_path` according to `mono_parent`, so that a `.gitmodules`
+    /// stanza missing from the live tree can still be looked up via
+    /// [`Self::resolve_submodule_url`]. Only `mono_parent` itself is
+    /// consulted, not its ancestors: returns `None` when there is no parent
+    /// mono commit, when it has no `top_bump` (for the top repo) or no
+    /// expanded bump at `repo_path` (for a submodule), or when that commit
+    /// has no known [`ThinCommit`].
+    fn current_thin_commit_for_push<'a>(
+        storage: &'a TopRepoCache,
+        mono_parent: Option<&Rc<MonoRepoCommit>>,
+        repo_name: &RepoName,
+        repo_path: &GitPath,
+    ) -> Option<&'a Rc<ThinCommit>> {
+        let mono_parent = mono_parent?;
+        let commit_id = match repo_name {
+            RepoName::Top => mono_parent.top_bump.clone()?.into_inner(),
+            RepoName::SubRepo(_) => {
+                let ExpandedOrRemovedSubmodule::Expanded(ExpandedSubmodule::Expanded(content)) =
+                    mono_parent.submodule_bumps.get(repo_path)?
+                else {
+                    return None;
+                };
+                content.orig_commit_id
+            }
+        };
+        storage.repos.get(repo_name)?.thin_commits.get(&commit_id)
+    }
+
+    /// Resolves credentials for `url` through the configured git credential
+    /// helper, consulting `cache` first so that a username/password prompt
+    /// for one host is only ever shown once, and shown again here once per
+    /// newly-encountered host, reused across every worker
+    /// [`Self::prefetch_missing_submodule_commits`] spawns for repos on
+    /// that same host rather than once per repo. `cache` is keyed by
+    /// scheme, host and port, and its lock is held while the helper runs so
+    /// that two workers for the same host do not both prompt.
+    fn cached_credentials(
+        cache: &Arc<Mutex<HashMap<String, gix::sec::identity::Account>>>,
+        url: &gix::Url,
+    ) -> Result<gix::sec::identity::Account> {
+        let url_string = url.to_bstring().to_string();
+        let host_key = format!(
+            "{}://{}:{:?}",
+            url.scheme.as_str(),
+            url.host().unwrap_or_default(),
+            url.port
+        );
+        let mut cache = cache.lock().expect("not poisoned");
+        if let Some(account) = cache.get(&host_key) {
+            return Ok(account.clone());
+        }
+        let outcome = gix::credentials::helper::invoke(
+            gix::credentials::helper::Action::get_for_url(url_string.as_str()),
+            &Default::default(),
+        )
+        .with_context(|| format!("Failed to resolve credentials for {url_string}"))?
+        .with_context(|| format!("No credentials available for {url_string}"))?;
+        let account = outcome.identity;
+        cache.insert(host_key, account.clone());
+        Ok(account)
+    }
+
+    /// Tops up the local object store with submodule commits that are
+    /// referenced by toprepo tips about to be expanded but are missing
+    /// locally, fetching each submodule's configured remote concurrently
+    /// instead of letting expansion fail with an "object missing" error.
+    ///
+    /// Two sources of missing commits are collected before any fetch
+    /// starts: (1) submodule-of-submodule bumps recorded on [`ThinCommit`]s
+    /// that a *previous* run already expanded (`storage.repos`), and (2)
+    /// first-level submodule bumps on toprepo commits that are brand new to
+    /// *this* run and thus have no `ThinCommit` yet - the common case of a
+    /// toprepo tip bumping a submodule pointer for the first time, found by
+    /// walking `new_toprepo_tips` down to the commits already recorded in
+    /// `storage.top_to_mono_map` and reading the gitlinks straight out of
+    /// each new commit's own tree (see
+    /// [`Self::collect_new_toprepo_submodule_bumps`]).
+    ///
+    /// Every submodule commit about to be fetched is first passed through
+    /// `guard` for its `--max-submodule-depth` check only: this pass is
+    /// flat, so each pair is exited again right away and a submodule that
+    /// (transitively) references itself is not reported as a cycle here.
+    fn prefetch_missing_submodule_commits(
+        &self,
+        repo: &gix::Repository,
+        storage: &TopRepoCache,
+        new_toprepo_tips: &[CommitId],
+        logger: &Logger,
+        progress: indicatif::MultiProgress,
+        guard: &mut SubmoduleExpansionGuard,
+    ) -> Result<()> {
+        const MAX_CONCURRENT_FETCHES: usize = 8;
+
+        // Shared across every worker below so a username/password prompt
+        // for a given host is only ever resolved once and reused for every
+        // other repo hosted on the same host.
+        let credential_cache: Arc<Mutex<HashMap<String, gix::sec::identity::Account>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        // Keyed by URL rather than `SubRepoName`, since (2) below discovers
+        // submodules that have no `SubRepoName` assigned yet - a URL read
+        // straight out of `.gitmodules` is all a fetch needs.
+        let mut missing_by_url: HashMap<String, (GitPath, gix::Url, HashSet<CommitId>)> =
+            HashMap::new();
+
+        for repo_data in storage.repos.values() {
+            for thin_commit in repo_data.thin_commits.values() {
+                for (path, bump) in thin_commit.submodule_bumps.iter() {
+                    let ThinSubmodule::AddedOrModified(content) = bump else {
+                        continue;
+                    };
+                    let Some(content_repo_name) = &content.repo_name else {
+                        continue;
+                    };
+                    if repo.find_object(content.commit_id).is_ok() {
+                        continue;
+                    }
+                    let Some(content_repo_data) = storage
+                        .repos
+                        .get(&RepoName::SubRepo(content_repo_name.clone()))
+                    else {
+                        continue;
+                    };
+                    // This loop is flat, not recursive, so `enter` is only
+                    // used for its `--max-submodule-depth` check here;
+                    // `exit` right away so an unrelated later bump that
+                    // happens to reference the same (url, commit_id) - e.g.
+                    // a submodule moved away and back - is never mistaken
+                    // for a cycle.
+                    guard.enter(
+                        &content_repo_data.url.to_bstring().to_string(),
+                        content.commit_id,
+                        thin_commit.depth + 1,
+                    )?;
+                    guard.exit();
+                    let entry = missing_by_url
+                        .entry(content_repo_data.url.to_bstring().to_string())
+                        .or_insert_with(|| {
+                            (path.clone(), content_repo_data.url.clone(), HashSet::new())
+                        });
+                    entry.2.insert(content.commit_id);
+                }
+            }
+        }
+        Self::collect_new_toprepo_submodule_bumps(
+            repo,
+            storage,
+            new_toprepo_tips,
+            guard,
+            &mut missing_by_url,
+        )?;
+        if missing_by_url.is_empty() {
+            return Ok(());
+        }
+
+        let repo_dir = self.gix_repo.git_dir().to_owned();
+        let jobs: Vec<_> = missing_by_url
+            .into_values()
+            .map(|(path, url, commit_ids)| (path, url, commit_ids.into_iter().collect_vec()))
+            .collect();
+        let errors: std::sync::Mutex<Vec<anyhow::Error>> = Default::default();
+        for batch in jobs.chunks(MAX_CONCURRENT_FETCHES) {
+            std::thread::scope(|scope| {
+                for (abs_path, url, commit_ids) in batch {
+                    let repo_dir = &repo_dir;
+                    let progress = progress.clone();
+                    let errors = &errors;
+                    let credential_cache = credential_cache.clone();
+                    scope.spawn(move || {
+                        let pb = progress.add(
+                            indicatif::ProgressBar::no_length().with_style(
+                                indicatif::ProgressStyle::default_spinner()
+                                    .template("{elapsed:>4} {msg} {pos}")
+                                    .unwrap(),
+                            ),
+                        );
+                        pb.set_message(format!("Pre-fetching {abs_path} ({url})"));
+                        let result = (|| -> Result<()> {
+                            let sub_repo = gix::open(repo_dir)
+                                .context("Failed to open repository for submodule prefetch")?;
+                            // Resolve (and cache) credentials ourselves and
+                            // embed them in the connect URL, rather than
+                            // relying on gix to invoke the credential helper
+                            // per connection, so a prompt for this host is
+                            // only ever shown once across every worker.
+                            let mut authenticated_url = url.clone();
+                            if let Ok(account) = Self::cached_credentials(&credential_cache, url)
+                            {
+                                authenticated_url.user = Some(account.username);
+                                authenticated_url.password = Some(account.password);
+                            }
+                            let remote = sub_repo
+                                .remote_at(authenticated_url)
+                                .context("Failed to configure the submodule remote")?
+                                .with_fetch_tags(gix::remote::fetch::Tags::None);
+                            let connection = remote
+                                .connect(gix::remote::Direction::Fetch)
+                                .with_context(|| format!("Failed to connect to {url}"))?;
+                            let refspecs: Vec<_> = commit_ids
+                                .iter()
+                                .map(|commit_id| {
+                                    format!(
+                                        "{commit_id}:refs/toprepo/prefetch/{commit_id}"
+                                    )
+                                })
+                                .collect();
+                            let prefetch_progress = IndicatifProgress::new(pb.clone());
+                            let bytes_progress = prefetch_progress.clone();
+                            let outcome = connection
+                                .prepare_fetch(prefetch_progress.clone(), Default::default())
+                                .context("Failed to prepare submodule prefetch")?
+                                .with_refspecs(
+                                    refspecs.iter().map(|s| s.as_str()),
+                                    gix::remote::Direction::Fetch,
+                                )
+                                .context("Failed to add prefetch refspecs")?
+                                .receive(prefetch_progress, &gix::interrupt::IS_INTERRUPTED)
+                                .with_context(|| format!("Failed to fetch commits from {url}"))?;
+                            if let Some(pack) = outcome.status.into_pack_receive_outcome() {
+                                pb.finish_with_message(format!(
+                                    "Pre-fetched {abs_path}: {} objects received, {} local, {} bytes",
+                                    pack.objects.unwrap_or(0),
+                                    pack.local_objects,
+                                    bytes_progress.bytes_received(),
+                                ));
+                            } else {
+                                pb.finish_and_clear();
+                            }
+                            Ok(())
+                        })();
+                        if let Err(err) = result {
+                            logger.warning(format!(
+                                "Failed to pre-fetch missing commits for {abs_path} ({url}): {err:#}"
+                            ));
+                            errors.lock().expect("not poisoned").push(err);
+                        }
+                    });
+                }
+            });
+        }
+        let errors = errors.into_inner().expect("not poisoned");
+        if !errors.is_empty() {
+            anyhow::bail!(
+                "Failed to pre-fetch missing submodule commits for {} submodule(s): {}",
+                errors.len(),
+                errors
+                    .iter()
+                    .map(|err| format!("{err:#}"))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Walks `tips` down to the commits already recorded in
+    /// `storage.top_to_mono_map`, and for every commit in between - the
+    /// ones about to be expanded for the first time - reads the gitlinks
+    /// straight out of its own tree so a submodule bumped for the very
+    /// first time by one of these commits is still found, even though no
+    /// [`ThinCommit`] for it exists anywhere in `storage` yet.
+    ///
+    /// Unlike a submodule's own tree (which is opaque until its commit is
+    /// fetched), the toprepo's tree is available locally right now, so this
+    /// only has to look at tree entries and `.gitmodules`, never fetch
+    /// anything itself - that part is left to the caller.
+    fn collect_new_toprepo_submodule_bumps(
+        repo: &gix::Repository,
+        storage: &TopRepoCache,
+        tips: &[CommitId],
+        guard: &mut SubmoduleExpansionGuard,
+        missing_by_url: &mut HashMap<String, (GitPath, gix::Url, HashSet<CommitId>)>,
+    ) -> Result<()> {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<CommitId> = tips.to_vec();
+        while let Some(commit_id) = stack.pop() {
+            if !seen.insert(commit_id) {
+                continue;
+            }
+            if storage.top_to_mono_map.contains_key(&TopRepoCommitId(commit_id)) {
+                // Already expanded in an earlier run: its own bumps were
+                // already handled back then, and anything it bumps further
+                // down is covered by the already-known-`ThinCommit` scan.
+                continue;
+            }
+            let commit = repo
+                .find_object(commit_id)
+                .with_context(|| format!("Failed to read toprepo commit {commit_id}"))?
+                .try_into_commit()
+                .with_context(|| format!("{commit_id} is not a commit"))?;
+            stack.extend(commit.parent_ids().map(|id| id.detach()));
+
+            let dot_gitmodules_path = GitPath::new(b".gitmodules".into());
+            let tree = commit.tree()?;
+            let Some(dot_gitmodules_entry) =
+                tree.lookup_entry_by_path(dot_gitmodules_path.to_path()?)?
+            else {
+                // No `.gitmodules` at all at this commit, so it cannot have
+                // introduced a submodule.
+                continue;
+            };
+            let dot_gitmodules_bytes = dot_gitmodules_entry
+                .object()?
+                .try_into_blob()
+                .with_context(|| format!("{dot_gitmodules_path} is not a blob at {commit_id}"))?
+                .take_data();
+            let git_modules_info = GitModulesInfo::parse_dot_gitmodules_bytes(
+                &dot_gitmodules_bytes,
+                dot_gitmodules_path.to_path()?.to_owned(),
+            )
+            .with_context(|| format!("Failed to parse {dot_gitmodules_path} at {commit_id}"))?;
+
+            let mut gitlinks = Vec::new();
+            collect_gitlinks_in_tree(
+                repo,
+                tree.id().detach(),
+                GitPath::new(b"".into()),
+                &mut gitlinks,
+            )?;
+            for (path, submod_commit_id) in gitlinks {
+                if repo.find_object(submod_commit_id).is_ok() {
+                    continue;
+                }
+                let Some((_submod_path, sub_url)) = git_modules_info.get_containing_submodule(&path)
+                else {
+                    continue;
+                };
+                let sub_url = match sub_url {
+                    Ok(sub_url) => sub_url,
+                    Err(err) => anyhow::bail!("{err:#}"),
+                };
+                let sub_url = EMPTY_GIX_URL.clone().join(sub_url);
+                // Flat, not recursive: only used for the
+                // `--max-submodule-depth` check, so `exit` right away (see
+                // the equivalent comment in `prefetch_missing_submodule_commits`).
+                guard.enter(&sub_url.to_bstring().to_string(), submod_commit_id, 1)?;
+                guard.exit();
+                let entry = missing_by_url
+                    .entry(sub_url.to_bstring().to_string())
+                    .or_insert_with(|| (path.clone(), sub_url.clone(), HashSet::new()));
+                entry.2.insert(submod_commit_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks every thin commit loaded so far and enforces `config`'s
+    /// [`SignaturePolicy`] for the repository it belongs to: a `Require`
+    /// violation aborts the refilter outright, a `Warn` violation is logged
+    /// but otherwise ignored, and `Off` (the default) skips the repository
+    /// entirely. An unsigned commit counts as a violation. This runs from
+    /// [`TopRepo::refilter`] once expansion is done; `fetch_toprepo` does
+    /// not check signatures.
+    ///
+    /// Every commit found valid is recorded in
+    /// [`TopRepoCache::verified_signatures`] and skipped on a later call, so
+    /// an incremental `refilter` only spawns `git verify-commit` for commits
+    /// that were not already known-good, instead of re-verifying the entire
+    /// history loaded so far on every call.
+    fn enforce_signature_policy(
+        &self,
+        storage: &mut TopRepoCache,
+        config: &crate::config::GitTopRepoConfig,
+        logger: &Logger,
+    ) -> Result<()> {
+        for (repo_name, repo_data) in &storage.repos {
+            let policy = config.signature_policy(repo_name);
+            if policy == SignaturePolicy::Off {
+                continue;
+            }
+            for thin_commit in repo_data.thin_commits.values() {
+                if storage.verified_signatures.contains(&thin_commit.commit_id) {
+                    continue;
+                }
+                let status =
+                    verify_commit_signature(&self.directory, thin_commit.commit_id)
+                        .with_context(|| {
+                            format!(
+                                "Failed to check the signature of {repo_name:?} commit {}",
+                                thin_commit.commit_id.to_hex()
+                            )
+                        })?;
+                let reason = match status {
+                    CommitSignatureStatus::Valid => {
+                        storage.verified_signatures.insert(thin_commit.commit_id);
+                        continue;
+                    }
+                    CommitSignatureStatus::Unsigned => "no signature found".to_owned(),
+                    CommitSignatureStatus::Invalid(reason) => reason,
+                };
+                let message = format!(
+                    "{repo_name:?} commit {} failed signature verification: {reason}",
+                    thin_commit.commit_id.to_hex()
+                );
+                match policy {
+                    SignaturePolicy::Off => unreachable!("checked above"),
+                    SignaturePolicy::Warn => logger.warning(message),
+                    SignaturePolicy::Require => anyhow::bail!(message),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bridges gix's progress reporting into the `indicatif` progress bar
+/// already used everywhere in this file, so a fetch shows live pack-receive
+/// progress instead of a bar that only changes once the fetch is already
+/// done (passing [`gix::progress::Discard`] reports nothing to update it
+/// with in the meantime). Every nested child progress gix creates (one per
+/// fetch phase: counting objects, receiving the pack, resolving deltas,
+/// updating refs) reports into the same bar, since a single spinner per
+/// fetch is all this file shows elsewhere too.
+///
+/// The "receiving" phase is additionally tracked into its own `bytes`
+/// counter (see [`Self::bytes_received`]), since that is the one phase
+/// whose step count is an actual byte total rather than an object/delta
+/// count, and callers want to report a true byte total once the fetch is
+/// done.
+#[derive(Clone)]
+struct IndicatifProgress {
+    bar: indicatif::ProgressBar,
+    step: Arc<std::sync::atomic::AtomicUsize>,
+    bytes: Arc<std::sync::atomic::AtomicU64>,
+    is_receiving_phase: bool,
+}
+
+impl IndicatifProgress {
+    fn new(bar: indicatif::ProgressBar) -> Self {
+        Self {
+            bar,
+            step: Default::default(),
+            bytes: Default::default(),
+            is_receiving_phase: false,
+        }
+    }
+
+    /// Total bytes reported so far by the "receiving objects"/"receiving
+    /// pack" phase specifically, the same byte total `git fetch`'s own
+    /// progress line shows for that phase, as opposed to [`Self::step`]'s
+    /// object/delta counts from every other phase.
+    fn bytes_received(&self) -> u64 {
+        self.bytes.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+impl gix::progress::Count for IndicatifProgress {
+    fn set(&self, step: usize) {
+        self.step.store(step, std::sync::atomic::Ordering::Relaxed);
+        if self.is_receiving_phase {
+            self.bytes.store(step as u64, std::sync::atomic::Ordering::Relaxed);
+        }
+        self.bar.set_position(step as u64);
+    }
+
+    fn step(&self) -> usize {
+        self.step.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn inc_by(&self, step: usize) {
+        self.set(self.step() + step);
+    }
+
+    fn counter(&self) -> gix::progress::StepShared {
+        Default::default()
+    }
+}
+
+impl gix::progress::Progress for IndicatifProgress {
+    fn init(&mut self, max: Option<usize>, _unit: Option<gix::progress::Unit>) {
+        self.bar.set_length(max.map(|max| max as u64).unwrap_or(u64::MAX));
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.bar.set_message(name);
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.bar.message())
+    }
+
+    fn id(&self) -> gix::progress::Id {
+        gix::progress::UNKNOWN
+    }
+
+    fn message(&self, _level: gix::progress::MessageLevel, message: String) {
+        self.bar.set_message(message);
+    }
+}
+
+impl gix::progress::NestedProgress for IndicatifProgress {
+    type SubProgress = IndicatifProgress;
+
+    fn add_child(&mut self, name: impl Into<String>) -> Self::SubProgress {
+        let name = name.into();
+        // gix names this phase the same way plain `git fetch` does
+        // ("Receiving objects"/"Receiving pack"), so match on that rather
+        // than guessing at gix's internal `Unit` representation.
+        let is_receiving_phase = name.to_ascii_lowercase().contains("receiv");
+        self.bar.set_message(name);
+        let mut child = self.clone();
+        child.is_receiving_phase = is_receiving_phase;
+        child
+    }
+
+    fn add_child_with_id(
+        &mut self,
+        name: impl Into<String>,
+        _id: gix::progress::Id,
+    ) -> Self::SubProgress {
+        self.add_child(name)
+    }
+}
+
+/// Guards recursive submodule expansion against submodules that
+/// (directly or transitively) include themselves, and against
+/// pathological configurations with unreasonably deep submodule nesting.
+///
+/// A submodule is identified by `(url, commit_id)` rather than just
+/// `commit_id`, since the same commit id existing in two unrelated
+/// repositories (a coincidence, not a cycle) must not trip the guard.
+#[derive(Debug, Default)]
+pub struct SubmoduleExpansionGuard {
+    /// `(url, commit_id)` pairs currently on the *active* recursion path,
+    /// pushed by [`Self::enter`] and popped by [`Self::exit`]. Tracking the
+    /// active path rather than every pair ever seen means two unrelated
+    /// bumps that happen to land on the same submodule commit (e.g. a
+    /// submodule moved away and later moved back to the exact commit it
+    /// started at) are never mistaken for a cycle - only a pair already
+    /// above us on the same call stack is.
+    active_path: Vec<(String, CommitId)>,
+    max_depth: Option<u32>,
+}
+
+impl SubmoduleExpansionGuard {
+    pub fn new(max_depth: Option<u32>) -> Self {
+        Self {
+            active_path: Vec::new(),
+            max_depth,
+        }
+    }
+
+    /// Records that expansion is about to recurse into `commit_id` of the
+    /// submodule at `url`, at nesting `depth` (as tracked by
+    /// [`ThinCommit::depth`] of the outermost commit being expanded).
+    /// Fails with a clear "submodule cycle detected" error if this exact
+    /// `(url, commit_id)` pair is already on the active path above us, or
+    /// if `depth` exceeds the configured `--max-submodule-depth`.
+    ///
+    /// A caller that does not actually recurse into `commit_id` (e.g. it
+    /// only wants the `--max-submodule-depth` check for a flat pass over
+    /// every bump, such as
+    /// [`TopRepo::prefetch_missing_submodule_commits`]) should call
+    /// [`Self::exit`] immediately afterwards so the pair never lingers on
+    /// the active path and falsely collides with an unrelated later bump
+    /// of the same commit. A genuine recursive expander should instead
+    /// call [`Self::exit`] only after returning from that recursive call -
+    /// that path lives in the submodule expander, which is not part of
+    /// this file.
+    pub fn enter(&mut self, url: &str, commit_id: CommitId, depth: u32) -> Result<()> {
+        if let Some(max_depth) = self.max_depth {
+            if depth > max_depth {
+                anyhow::bail!(
+                    "Submodule nesting depth {depth} exceeds --max-submodule-depth={max_depth} \
+                     while expanding {url} at {}",
+                    commit_id.to_hex()
+                );
+            }
+        }
+        if self
+            .active_path
+            .iter()
+            .any(|(active_url, active_commit_id)| active_url == url && *active_commit_id == commit_id)
+        {
+            anyhow::bail!(
+                "submodule cycle detected: {url} at {} is already being expanded higher up this recursion",
+                commit_id.to_hex()
+            );
+        }
+        self.active_path.push((url.to_owned(), commit_id));
+        Ok(())
+    }
+
+    /// Pops the most recently entered `(url, commit_id)` pair. Must be
+    /// called once recursion into the pair passed to the matching
+    /// [`Self::enter`] call has returned (or immediately, for a caller that
+    /// never actually recurses - see [`Self::enter`]'s doc comment).
+    pub fn exit(&mut self) {
+        self.active_path.pop();
+    }
+}
+
+/// Controls whether [`TopRepo::update_refs`] is allowed to delete a
+/// `refs/remotes/origin/*` ref that disappeared upstream, or must instead
+/// abort the whole transaction for the caller to review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefUpdateSafety {
+    AllowUnguardedDeletes,
+    RejectUnguardedDeletes,
+}
+
+/// A preview of what [`TopRepo::update_refs`] would do (or did), so that
+/// `git toprepo` can show a refilter's effect before, or instead of,
+/// touching `refs/remotes/origin/*`.
+#[derive(Debug, Clone, Default)]
+pub struct RefUpdatePlan {
+    pub created: Vec<(FullName, gix::refs::Target)>,
+    pub updated: Vec<(FullName, gix::refs::Target, gix::refs::Target)>,
+    pub deleted: Vec<(FullName, gix::refs::Target)>,
+}
+
+/// The result of a single `fetch_toprepo*` call: one [`RefUpdate`] per ref
+/// that was touched (or would have been touched, in a dry run) under
+/// `refs/namespaces/top/*`.
+#[derive(Debug, Clone)]
+pub struct FetchOutcome {
+    pub updates: Vec<RefUpdate>,
+}
+
+/// How a single namespaced ref changed as part of a fetch, modeled on
+/// gitoxide's `fetch::refs::update::Mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefUpdateMode {
+    New,
+    FastForward,
+    Forced,
+    Unchanged,
+    Rejected,
+}
+
+impl RefUpdateMode {
+    fn from_gix(mode: &gix::remote::fetch::refs::update::Mode) -> Self {
+        use gix::remote::fetch::refs::update::Mode as GixMode;
+        match mode {
+            GixMode::New => RefUpdateMode::New,
+            GixMode::FastForward => RefUpdateMode::FastForward,
+            GixMode::Forced => RefUpdateMode::Forced,
+            GixMode::NoChange => RefUpdateMode::Unchanged,
+            GixMode::Rejected(_) => RefUpdateMode::Rejected,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RefUpdate {
+    pub name: FullName,
+    pub mode: RefUpdateMode,
+    pub old: Option<CommitId>,
+    pub new: Option<CommitId>,
 }
 
 #[serde_as]
@@ -919,6 +2060,21 @@ impl Display for TopRepoCommitId {
 
 pub type RepoStates = HashMap<RepoName, RepoData>;
 
+/// Bumped whenever the on-disk shape or semantics of [`TopRepoCache`]
+/// changes in a way that makes a cache written by a previous version
+/// unsafe to trust as-is.
+pub const CACHE_SCHEMA_VERSION: u32 = 1;
+
+/// Framing stored alongside a serialized [`TopRepoCache`] so that a stale
+/// schema or corrupted/truncated file is detected before the cache is
+/// used, rather than causing confusing errors (or silently wrong
+/// behavior) further down the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheFrameHash {
+    pub schema_version: u32,
+    pub content_hash: u64,
+}
+
 // TODO: Use `Rc` to all the `GitPath`s and `ObjectId`s to avoid memory duplication.
 // Is it really more efficient to use `Rc`?
 #[derive(Default)]
@@ -929,6 +2085,398 @@ pub struct TopRepoCache {
     /// Mapping from top repo commit to mono repo commit.
     pub top_to_mono_map: HashMap<TopRepoCommitId, Rc<MonoRepoCommit>>,
     pub dedup: GitFastExportImportDedupCache,
+    /// Change-Id per `(push_url, parent submodule commit ids)`, reused the
+    /// next time a mono commit sitting on the exact same submodule parents
+    /// is pushed to the same submodule, so it updates the existing Gerrit
+    /// (or similar) review instead of creating a new one.
+    ///
+    /// Keying by the parent lineage rather than the pushed commit's own id
+    /// is what makes this survive a plain amend of the tip commit: amending
+    /// rewrites the commit's id, tree and message but, by definition,
+    /// leaves its parents untouched, so the same key is looked up again and
+    /// the same Change-Id comes back. A Change-Id trailer already present
+    /// in the message (see [`TopRepo::rewrite_push_message`]'s
+    /// `explicit_change_id`) still takes priority over this map and is the
+    /// only thing that survives a rebase that also changes the parents.
+    pub change_ids: HashMap<(String, Vec<CommitId>), String>,
+    /// Commit ids [`TopRepo::enforce_signature_policy`] already found to
+    /// pass `config`'s [`SignaturePolicy`], so a later, incremental
+    /// `refilter` only re-verifies commits new since the last call instead
+    /// of shelling out to `git verify-commit` for every historical commit
+    /// loaded so far.
+    pub verified_signatures: HashSet<CommitId>,
+}
+
+impl TopRepoCache {
+    /// A deterministic, order-independent content hash over
+    /// `top_to_mono_map`, `monorepo_commits` and the repo/thin-commit
+    /// tables, computed at save time and re-verified at load time so that a
+    /// stale or corrupted on-disk cache is never silently trusted.
+    ///
+    /// Each map is hashed by combining a hash of every entry with a
+    /// commutative operator (`wrapping_add`), so the result does not depend
+    /// on iteration order. Optional fields are hashed as `Option` values:
+    /// `None` contributes only its discriminant, `Some` its discriminant and
+    /// value.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash: u64 = 0;
+        for (top_id, mono_commit) in &self.top_to_mono_map {
+            hash = hash.wrapping_add(hash_one(&(
+                "top_to_mono_map",
+                top_id.to_string(),
+                mono_commit.top_bump.as_ref().map(|id| id.to_string()),
+            )));
+        }
+        for (mono_id, mono_commit) in &self.monorepo_commits {
+            if *mono_id == MonoRepoCommitId::dummy() {
+                // Transient placeholder used while importing; including it
+                // would make a lossy round-trip (where the real id was not
+                // yet known) hash differently from a cache saved once the
+                // id was resolved, even though the logical content matches.
+                continue;
+            }
+            hash = hash.wrapping_add(hash_one(&(
+                "monorepo_commits",
+                mono_id.to_string(),
+                mono_commit.top_bump.as_ref().map(|id| id.to_string()),
+                mono_commit
+                    .submodule_bumps
+                    .iter()
+                    .map(|(path, bump)| (path.to_string(), canonical_expanded_bump(bump)))
+                    .collect::<BTreeMap<_, _>>(),
+            )));
+        }
+        for (repo_name, repo_data) in &self.repos {
+            for thin_commit in repo_data.thin_commits.values() {
+                hash = hash.wrapping_add(hash_one(&(
+                    "thin_commits",
+                    format!("{repo_name:?}"),
+                    thin_commit.commit_id.to_string(),
+                    thin_commit.tree_id.to_string(),
+                    thin_commit.dot_gitmodules.as_ref().map(|id| id.to_string()),
+                    thin_commit
+                        .submodule_bumps
+                        .iter()
+                        .map(|(path, bump)| (path.to_string(), canonical_thin_bump(bump)))
+                        .collect::<BTreeMap<_, _>>(),
+                )));
+            }
+        }
+        hash
+    }
+
+    /// Returns whether `self` still matches a previously computed
+    /// [`Self::content_hash`], i.e. whether the loaded cache can be trusted
+    /// as-is rather than being rebuilt from scratch.
+    pub fn verify_content_hash(&self, expected: u64) -> bool {
+        self.content_hash() == expected
+    }
+
+    /// Like [`Self::content_hash`] but also binds the result to
+    /// [`CACHE_SCHEMA_VERSION`], so that a cache written by an older or
+    /// newer version of this program is rejected even if its serialized
+    /// shape happens to still deserialize successfully.
+    pub fn framed_content_hash(&self) -> CacheFrameHash {
+        CacheFrameHash {
+            schema_version: CACHE_SCHEMA_VERSION,
+            content_hash: self.content_hash(),
+        }
+    }
+
+    /// Returns whether `self` still matches a previously computed
+    /// [`Self::framed_content_hash`]. A mismatch covers both content drift
+    /// and a schema version bump, either of which means the cache must be
+    /// discarded and rebuilt from the repos rather than trusted as-is.
+    pub fn verify_framed_content_hash(&self, expected: CacheFrameHash) -> bool {
+        expected.schema_version == CACHE_SCHEMA_VERSION
+            && self.verify_content_hash(expected.content_hash)
+    }
+
+    /// The self-healing half of the cache, for a caller that owns the
+    /// `TopRepoCache` by value (e.g. a future on-disk loader deserializing
+    /// straight into one): pair it with the [`CacheFrameHash`] stored
+    /// alongside it on disk and call this immediately afterwards. `Some`
+    /// means the cache is intact and can be used as-is; `None` means it is
+    /// stale or corrupted and the caller must discard it and rebuild from
+    /// the repos instead of trusting it.
+    ///
+    /// [`TopRepo::refilter`] and [`TopRepo::expand_toprepo_refs`] already
+    /// wire up the equivalent check for their `&mut TopRepoCache` callers
+    /// (see `TopRepo::discard_storage_if_stale`), which verifies in place
+    /// instead of consuming `self` by value.
+    pub fn discard_if_stale(self, expected: CacheFrameHash) -> Option<Self> {
+        self.verify_framed_content_hash(expected).then_some(self)
+    }
+
+    /// The complete linear mono-repo history of `start`: every ancestor
+    /// reachable via `MonoRepoParent::Mono` edges, analogous to the full
+    /// changelog of a repository, newest first like `git log`. Each entry
+    /// is paired with the top-repo commit it bumped, if any.
+    pub fn mono_history(
+        &self,
+        start: &Rc<MonoRepoCommit>,
+    ) -> impl Iterator<Item = (MonoRepoCommitId, Option<CommitId>)> {
+        self.walk_mono_history(start, None)
+    }
+
+    /// The history of `start` filtered to a specific submodule `path`,
+    /// analogous to path-filtered `git log`: mono commits that did not
+    /// actually bump `path` (or a path contained under it, or a path that
+    /// contains it, so a directory-level query still surfaces bumps of
+    /// submodules nested underneath) are skipped. Each yielded entry
+    /// resolves back to the underlying `SubmoduleContent::orig_commit_id`,
+    /// so callers can cross-reference the original sub-repo commit without
+    /// re-expanding trees.
+    pub fn submodule_history(
+        &self,
+        start: &Rc<MonoRepoCommit>,
+        path: &GitPath,
+    ) -> impl Iterator<Item = (MonoRepoCommitId, Option<CommitId>)> {
+        self.walk_mono_history(start, Some(path))
+    }
+
+    fn walk_mono_history(
+        &self,
+        start: &Rc<MonoRepoCommit>,
+        path: Option<&GitPath>,
+    ) -> impl Iterator<Item = (MonoRepoCommitId, Option<CommitId>)> {
+        let mut result = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue = vec![start.clone()];
+        while let Some(commit) = queue.pop() {
+            if !visited.insert(Rc::as_ptr(&commit) as usize) {
+                continue;
+            }
+            let bump = match path {
+                None => commit.top_bump.as_ref().map(|id| **id),
+                Some(path) => {
+                    // `submodule_bumps` is a `HashMap`, so its iteration
+                    // order is not deterministic across runs; when more than
+                    // one submodule under `path` was bumped in the same
+                    // commit, sort the candidates by path first so the
+                    // returned commit id is stable instead of depending on
+                    // hash-map iteration order.
+                    let mut candidates: Vec<_> = commit
+                        .submodule_bumps
+                        .iter()
+                        .filter(|(bump_path, _)| path_contains(bump_path, path))
+                        .collect();
+                    candidates.sort_by_key(|(bump_path, _)| (*bump_path).clone());
+                    candidates.into_iter().find_map(|(_bump_path, bump)| match bump {
+                        ExpandedOrRemovedSubmodule::Expanded(submod) => {
+                            Some(*submod.get_orig_commit_id())
+                        }
+                        ExpandedOrRemovedSubmodule::Removed => None,
+                    })
+                }
+            };
+            // With a path filter, only yield commits that actually bumped
+            // the requested submodule; always keep walking ancestors either
+            // way so the rest of the filtered history isn't lost.
+            if path.is_none() || bump.is_some() {
+                if let Some(id) = self
+                    .monorepo_commit_ids
+                    .get(&RcKey::from(commit.clone()))
+                    .cloned()
+                {
+                    result.push((id, bump, commit.depth));
+                }
+            }
+            for parent in &commit.parents {
+                if let MonoRepoParent::Mono(parent_rc) = parent {
+                    queue.push(parent_rc.clone());
+                }
+            }
+        }
+        // Order newest-first like `git log`: `depth` (the longest distance
+        // from the history root) stands in for chronological order here,
+        // since committer time isn't tracked on `MonoRepoCommit` itself;
+        // ties (e.g. both sides of a merge) are broken by id so the result
+        // is deterministic across runs instead of reflecting traversal
+        // order, which a plain DFS stack `pop()` does not guarantee.
+        result.sort_by(|(id_a, _, depth_a), (id_b, _, depth_b)| {
+            depth_b.cmp(depth_a).then_with(|| id_a.cmp(id_b))
+        });
+        result.into_iter().map(|(id, bump, _depth)| (id, bump))
+    }
+}
+
+/// Recursively collects every gitlink (submodule) tree entry under `tree`,
+/// as `(path relative to the tree root, commit id)` pairs, descending into
+/// ordinary subdirectories so a submodule nested a few levels deep in the
+/// toprepo is found too. Does not descend into a gitlink itself - that
+/// would require the submodule's own commit, which might be exactly the
+/// object this is trying to determine is missing.
+fn collect_gitlinks_in_tree(
+    repo: &gix::Repository,
+    tree_id: TreeId,
+    prefix: GitPath,
+    out: &mut Vec<(GitPath, CommitId)>,
+) -> Result<()> {
+    let tree_object = repo
+        .find_object(tree_id)
+        .with_context(|| format!("Failed to read tree {tree_id}"))?
+        .try_into_tree()
+        .with_context(|| format!("{tree_id} is not a tree"))?;
+    for entry in &tree_object.decode()?.entries {
+        let path = prefix.join(&GitPath::new(entry.filename.to_owned()));
+        if entry.mode.is_commit() {
+            out.push((path, entry.oid.to_owned()));
+        } else if entry.mode.is_tree() {
+            collect_gitlinks_in_tree(repo, entry.oid.to_owned(), path, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `path` is exactly `bump_path`, nested under it, or
+/// encloses it, i.e. whether a submodule bumped at `bump_path` is relevant
+/// to a history query for `path`: a query for a directory should still
+/// surface bumps of submodules nested underneath it, not just an exact or
+/// ancestor match.
+fn path_contains(bump_path: &GitPath, path: &GitPath) -> bool {
+    if bump_path == path {
+        return true;
+    }
+    let bump_prefix = [bump_path.as_bytes(), b"/"].concat();
+    if path.as_bytes().starts_with(&bump_prefix) {
+        return true;
+    }
+    let path_prefix = [path.as_bytes(), b"/"].concat();
+    bump_path.as_bytes().starts_with(&path_prefix)
+}
+
+/// A [`std::hash::Hasher`] that just appends every byte it is fed to a
+/// buffer, so [`hash_one`] can finish it off with [`sha1_hex`] instead of
+/// `DefaultHasher`'s SipHash, whose output the standard library explicitly
+/// does not guarantee stable across compiler versions/builds. Unsuitable
+/// for `HashMap` (no mixing, no speed), but that's not what it's used for
+/// here.
+struct ByteCollectingHasher(Vec<u8>);
+
+impl std::hash::Hasher for ByteCollectingHasher {
+    fn finish(&self) -> u64 {
+        unreachable!("ByteCollectingHasher is only ever finished via hash_one/sha1_hex")
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+/// Hashes `value` down to 64 bits via [`sha1_hex`] rather than
+/// `DefaultHasher`, so the result stays stable across compiler
+/// versions/builds and is safe to persist on disk and compare against a
+/// cache written by a different build ([`TopRepoCache::content_hash`]).
+fn hash_one<T: Hash>(value: &T) -> u64 {
+    let mut hasher = ByteCollectingHasher(Vec::new());
+    value.hash(&mut hasher);
+    let digest = sha1_hex(&hasher.0);
+    u64::from_str_radix(&digest[..16], 16).expect("sha1_hex always returns 40 hex digits")
+}
+
+/// Computes a SHA-1 digest of `data` and returns it as 40 lowercase hex
+/// digits, the way `git hash-object`/Gerrit's commit-msg hook would. Used
+/// by [`TopRepo::generate_change_id`] instead of a general-purpose hasher
+/// so the resulting id has the full 160 bits of a real SHA-1 rather than a
+/// 64-bit hash zero-padded out to the expected width.
+fn sha1_hex(data: &[u8]) -> String {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let bit_len = (data.len() as u64) * 8;
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&bit_len.to_be_bytes());
+
+    for chunk in padded.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | ((!b) & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+
+    h.iter().map(|word| format!("{word:08x}")).collect()
+}
+
+/// Canonical, stable representation of a [`ThinSubmodule`] bump for
+/// [`TopRepoCache::content_hash`]. Built from the variant's own fields
+/// instead of `{:?}`, so the persisted integrity key does not change just
+/// because a `Debug` impl was reformatted.
+fn canonical_thin_bump(bump: &ThinSubmodule) -> (&'static str, Option<String>, Option<String>) {
+    match bump {
+        ThinSubmodule::AddedOrModified(content) => (
+            "added_or_modified",
+            content.repo_name.as_ref().map(|name| format!("{name:?}")),
+            Some(content.commit_id.to_string()),
+        ),
+        ThinSubmodule::Removed => ("removed", None, None),
+    }
+}
+
+/// Canonical, stable representation of an [`ExpandedOrRemovedSubmodule`]
+/// bump for [`TopRepoCache::content_hash`], for the same reason as
+/// [`canonical_thin_bump`].
+fn canonical_expanded_bump(
+    bump: &ExpandedOrRemovedSubmodule,
+) -> (&'static str, Option<String>, Option<String>) {
+    let ExpandedOrRemovedSubmodule::Expanded(expanded) = bump else {
+        return ("removed", None, None);
+    };
+    match expanded {
+        ExpandedSubmodule::Expanded(content) => (
+            "expanded",
+            Some(format!("{:?}", content.repo_name)),
+            Some(content.orig_commit_id.to_string()),
+        ),
+        ExpandedSubmodule::KeptAsSubmodule(commit_id) => {
+            ("kept_as_submodule", None, Some(commit_id.to_string()))
+        }
+        ExpandedSubmodule::CommitMissingInSubRepo(content) => (
+            "commit_missing_in_sub_repo",
+            Some(format!("{:?}", content.repo_name)),
+            Some(content.orig_commit_id.to_string()),
+        ),
+        ExpandedSubmodule::UnknownSubmodule(commit_id) => {
+            ("unknown_submodule", None, Some(commit_id.to_string()))
+        }
+        ExpandedSubmodule::RegressedNotFullyImplemented(content) => (
+            "regressed_not_fully_implemented",
+            Some(format!("{:?}", content.repo_name)),
+            Some(content.orig_commit_id.to_string()),
+        ),
+    }
 }
 
 #[serde_as]
@@ -1022,7 +2570,15 @@ impl MonoRepoCommit {
                 ExpandedOrRemovedSubmodule::Removed => {
                     submodule_paths = Rc::new({
                         let mut paths = submodule_paths.as_ref().clone();
-                        paths.remove(path);
+                        // Leftover files under a removed submodule are
+                        // ordinary tree entries of the enclosing repo from
+                        // now on, so any nested submodule that used to live
+                        // under it must stop being treated as expanded too,
+                        // rather than lingering as a phantom submodule.
+                        let removed_prefix = [path.as_bytes(), b"/"].concat();
+                        paths.retain(|existing| {
+                            existing != path && !existing.as_bytes().starts_with(&removed_prefix)
+                        });
                         paths
                     });
                 }
@@ -1036,6 +2592,113 @@ impl MonoRepoCommit {
             submodule_paths,
         })
     }
+
+    /// Synthesizes the intermediate "Resetting to X" mono commit used when a
+    /// submodule pointer regresses to an earlier or unrelated commit (see
+    /// [`ExpandedSubmodule::RegressedNotFullyImplemented`]). It has `mono_parent` as its only
+    /// parent and bumps `path` straight to `reset_to`, so that the forward
+    /// history can be expanded on top of it without a merge line crossing
+    /// the revert point.
+    pub fn new_reset_rc(
+        mono_parent: Rc<MonoRepoCommit>,
+        path: GitPath,
+        reset_to: SubmoduleContent,
+    ) -> Rc<MonoRepoCommit> {
+        Self::new_rc(
+            vec![MonoRepoParent::Mono(mono_parent)],
+            None,
+            HashMap::from([(
+                path,
+                ExpandedOrRemovedSubmodule::Expanded(ExpandedSubmodule::Expanded(reset_to)),
+            )]),
+        )
+    }
+}
+
+/// A single commit ready to be emitted during [`topo_sort_mono_commits`],
+/// ordered by committer timestamp and then by commit id so that ties are
+/// still broken deterministically.
+#[derive(PartialEq, Eq)]
+struct ReadyMonoCommit {
+    committer_time: i64,
+    commit_id: CommitId,
+}
+
+impl PartialOrd for ReadyMonoCommit {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReadyMonoCommit {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.committer_time
+            .cmp(&other.committer_time)
+            .then_with(|| self.commit_id.to_hex().cmp(&other.commit_id.to_hex()))
+    }
+}
+
+/// Produces a stable, topologically-valid order over `commits`: parents
+/// strictly precede children following `MonoRepoParent::Mono` edges, and
+/// whenever several commits are simultaneously ready, the one with the
+/// earliest `committer_time` (then the smallest commit id) is emitted
+/// first. Commits of parallel branches are therefore interleaved by
+/// committer time, not emitted one branch at a time. The result is
+/// reproducible across runs regardless of the order `commits` was built
+/// in.
+pub fn topo_sort_mono_commits(
+    commits: &HashMap<CommitId, Rc<MonoRepoCommit>>,
+    committer_time: impl Fn(&CommitId) -> i64,
+) -> Vec<CommitId> {
+    let ptr_to_id: HashMap<usize, CommitId> = commits
+        .iter()
+        .map(|(id, commit)| (Rc::as_ptr(commit) as usize, *id))
+        .collect();
+
+    let mut children: HashMap<CommitId, Vec<CommitId>> = HashMap::new();
+    let mut in_degree: HashMap<CommitId, usize> = commits.keys().map(|id| (*id, 0)).collect();
+    for (id, commit) in commits {
+        for parent in &commit.parents {
+            let MonoRepoParent::Mono(parent_rc) = parent else {
+                continue;
+            };
+            let Some(parent_id) = ptr_to_id.get(&(Rc::as_ptr(parent_rc) as usize)) else {
+                continue;
+            };
+            children.entry(*parent_id).or_default().push(*id);
+            *in_degree.get_mut(id).expect("id was inserted above") += 1;
+        }
+    }
+
+    let mut ready: std::collections::BinaryHeap<std::cmp::Reverse<ReadyMonoCommit>> = in_degree
+        .iter()
+        .filter(|(_, degree)| **degree == 0)
+        .map(|(id, _)| {
+            std::cmp::Reverse(ReadyMonoCommit {
+                committer_time: committer_time(id),
+                commit_id: *id,
+            })
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(commits.len());
+    while let Some(std::cmp::Reverse(next)) = ready.pop() {
+        order.push(next.commit_id);
+        let Some(kids) = children.get(&next.commit_id) else {
+            continue;
+        };
+        for kid in kids {
+            let degree = in_degree.get_mut(kid).expect("kid was inserted above");
+            *degree -= 1;
+            if *degree == 0 {
+                ready.push(std::cmp::Reverse(ReadyMonoCommit {
+                    committer_time: committer_time(kid),
+                    commit_id: *kid,
+                }));
+            }
+        }
+    }
+    order
 }
 
 #[serde_as]
@@ -1062,6 +2725,16 @@ pub enum ExpandedSubmodule {
     /// reason is that there should not be merge lines over a revert point as
     /// those merges makes no sense.
     ///
+    /// NOT FULLY IMPLEMENTED: [`ThinCommit::is_regression`] and
+    /// [`MonoRepoCommit::new_reset_rc`] are wired together by
+    /// [`TopRepo::resolve_submodule_regressions`] for the single
+    /// mono-parent case handled when injecting one submodule ref onto
+    /// HEAD, so this variant is never constructed for that path. A
+    /// regression surfacing during a full refilter/expand, where the
+    /// detection would have to live in `get_recursive_submodule_bumps`/
+    /// `expand_inner_submodules`, is still unhandled, so this variant is
+    /// never constructed there either.
+    ///
     /// Consider the following example:
     /// ```txt
     /// Submodule:
@@ -1098,9 +2771,11 @@ pub enum ExpandedSubmodule {
     /// |/
     /// * A with y
     /// ```
-    // TODO: Implement this in the
-    // TopRepoExpander::get_recursive_submodule_bumps() or extract the
-    // information from TopRepoExpander::expand_inner_submodules().
+    /// The "Resetting to x" commit is synthesized by
+    /// [`MonoRepoCommit::new_reset_rc`]: it has a single parent, the prior
+    /// mono commit, and its only change is forcing the submodule back to
+    /// the regressed target, so the forward history (`y`) can be expanded on
+    /// top of it without a merge line across the revert point.
     RegressedNotFullyImplemented(SubmoduleContent),
 }
 
@@ -1150,6 +2825,10 @@ pub struct RepoData {
     /// commit have different committer but otherwise are exactly the same.
     /// The values represent the latest imported or exported commit id.
     pub dedup_cache: HashMap<WithoutCommitterId, CommitId>,
+    /// Tag names known for this repository, keyed by the commit they point
+    /// to (after peeling). Used by [`Self::describe`] to name commits the
+    /// way `git describe` would.
+    pub tags: HashMap<CommitId, String>,
 }
 
 impl RepoData {
@@ -1158,16 +2837,163 @@ impl RepoData {
             url,
             thin_commits: HashMap::new(),
             dedup_cache: HashMap::new(),
+            tags: HashMap::new(),
+        }
+    }
+
+    /// Populates [`Self::tags`] from every `refs/tags/*` ref found under
+    /// `ref_prefix` in `repo`, peeling annotated tags to the commit they
+    /// point to (a lightweight tag already points straight at one). Without
+    /// this, [`Self::describe`]/[`ThinCommit::describe_via_submodules`] have
+    /// no tag names to report and always return `None`.
+    pub fn load_tags(&mut self, repo: &gix::Repository, ref_prefix: &str) -> Result<()> {
+        let tags_prefix = format!("{ref_prefix}refs/tags/");
+        for r in repo
+            .references()?
+            .prefixed(BStr::new(tags_prefix.as_bytes()))?
+        {
+            let r = r.map_err(|err| anyhow::anyhow!("Failed while iterating tag refs: {err:#}"))?;
+            let tag_name = String::from_utf8_lossy(
+                r.name()
+                    .as_bstr()
+                    .strip_prefix(tags_prefix.as_bytes())
+                    .unwrap_or(r.name().as_bstr()),
+            )
+            .into_owned();
+            let commit = r
+                .clone()
+                .follow_to_object()
+                .with_context(|| format!("Failed to resolve tag ref {}", r.name().as_bstr()))?
+                .object()?
+                .peel_to_commit()
+                .with_context(|| format!("Failed to peel tag {tag_name} to a commit"))?;
+            self.tags.insert(CommitId::from(commit.id), tag_name);
+        }
+        Ok(())
+    }
+
+    /// Names `commit_id` the way `git describe --tags` would: walks the
+    /// commit's ancestors, nearest first, until one carries a tag in
+    /// [`Self::tags`], then renders `<tag>-<N>-g<shorthash>` where `N` is
+    /// the number of commits between the tag and `commit_id` (omitted,
+    /// along with the hash, when `N` is zero). Returns `None` if no
+    /// ancestor is tagged.
+    pub fn describe(&self, commit_id: CommitId) -> Option<String> {
+        self.describe_with_distance(commit_id)
+            .map(|described| described.description)
+    }
+
+    /// Like [`Self::describe`], but also returns the raw `distance: u32`
+    /// the description was rendered from, so a caller that needs to
+    /// compare distances (e.g. [`ThinCommit::describe_via_submodules`]'s
+    /// tie-break) can do so directly instead of re-parsing the rendered
+    /// `<tag>-<N>-g<hash>` string - which is ambiguous for a tag name that
+    /// itself contains a numeric-looking `-N` suffix.
+    pub fn describe_with_distance(&self, commit_id: CommitId) -> Option<Described> {
+        let start = self.thin_commits.get(&commit_id)?.clone();
+        // All parent edges have the same weight, so plain BFS already
+        // visits ancestors in non-decreasing distance order - no need for
+        // a priority queue to find the nearest tag.
+        let mut visited = HashSet::new();
+        visited.insert(start.commit_id);
+        let mut queue = std::collections::VecDeque::new();
+        queue.push_back((start, 0u32));
+        while let Some((node, distance)) = queue.pop_front() {
+            if let Some(tag) = self.tags.get(&node.commit_id) {
+                let description = if distance == 0 {
+                    tag.clone()
+                } else {
+                    format!("{tag}-{distance}-g{}", commit_id.to_hex_with_len(7))
+                };
+                return Some(Described {
+                    description,
+                    distance,
+                });
+            }
+            for parent in &node.parents {
+                if visited.insert(parent.commit_id) {
+                    queue.push_back((parent.clone(), distance + 1));
+                }
+            }
         }
+        None
     }
 }
 
+/// The result of [`RepoData::describe_with_distance`]: a `git
+/// describe`-style name together with the number of commits between the
+/// tag it was computed from and the described commit, as a plain `u32`
+/// rather than something a caller would need to re-parse out of
+/// `description`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Described {
+    pub description: String,
+    pub distance: u32,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum ThinSubmodule {
     AddedOrModified(ThinSubmoduleContent),
     Removed,
 }
 
+/// Policy controlling whether commit signatures are checked while
+/// importing superrepo and submodule commits, and what happens when a
+/// signature fails verification against the configured allowed-signers
+/// keyring.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignaturePolicy {
+    /// Do not check commit signatures at all.
+    #[default]
+    Off,
+    /// Check commit signatures and log a warning on failure, but continue.
+    Warn,
+    /// Abort `refilter` if any checked commit is unsigned or fails
+    /// verification.
+    Require,
+}
+
+/// Result of checking a single commit's signature against the configured
+/// allowed-signers keyring (`gpg.ssh.allowedSignersFile` or the GPG
+/// keyring, depending on the signature type).
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CommitSignatureStatus {
+    /// The commit carries no signature.
+    Unsigned,
+    /// The signature was verified successfully.
+    Valid,
+    /// The commit is signed but verification failed, with `git
+    /// verify-commit`'s explanation of why.
+    Invalid(String),
+}
+
+/// Verifies `commit_id`'s signature by shelling out to `git verify-commit`,
+/// classifying the result as a [`CommitSignatureStatus`]. An unsigned
+/// commit is reported as [`CommitSignatureStatus::Unsigned`] rather than
+/// an error, since it is the caller's [`SignaturePolicy`] - not this
+/// function - that decides whether that is acceptable.
+pub fn verify_commit_signature(
+    directory: &std::path::Path,
+    commit_id: CommitId,
+) -> Result<CommitSignatureStatus> {
+    let mut cmd = git_command(directory);
+    cmd.arg("verify-commit")
+        .arg("--raw")
+        .arg(commit_id.to_string());
+    match cmd.check_success_with_stderr() {
+        Ok(_) => Ok(CommitSignatureStatus::Valid),
+        Err(err) => {
+            let message = err.to_string();
+            if message.contains("no signature found") {
+                Ok(CommitSignatureStatus::Unsigned)
+            } else {
+                Ok(CommitSignatureStatus::Invalid(message))
+            }
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ThinSubmoduleContent {
@@ -1263,6 +3089,66 @@ impl ThinCommit {
         false
     }
 
+    /// Returns true if moving a submodule pointer from `self` to `new_tip`
+    /// would be a regression, i.e. `new_tip` is an ancestor of `self` or
+    /// unrelated to it rather than a proper descendant. Used to detect when
+    /// a submodule bump should become [`ExpandedSubmodule::RegressedNotFullyImplemented`]
+    /// instead of a normal forward bump.
+    pub fn is_regression(&self, new_tip: &ThinCommit) -> bool {
+        new_tip.commit_id != self.commit_id && !new_tip.is_descendant_of(self)
+    }
+
+    /// Returns the parent to collapse onto if `self` is a trivial merge,
+    /// i.e. a merge commit whose tree is identical to one parent's tree
+    /// while every other parent is already an ancestor of that parent. Such
+    /// a merge carries no submodule bump of its own - it only exists to
+    /// fast-forward the mono repo's view of a submodule that had already
+    /// moved - so it can be collapsed away during expansion instead of
+    /// producing an empty bump commit.
+    pub fn is_trivial_merge(&self) -> Option<&Rc<ThinCommit>> {
+        if self.parents.len() < 2 {
+            return None;
+        }
+        let (same_tree_parent, other_parents) = self
+            .parents
+            .iter()
+            .find(|parent| parent.tree_id == self.tree_id)
+            .map(|same_tree_parent| {
+                (
+                    same_tree_parent,
+                    self.parents
+                        .iter()
+                        .filter(|parent| !Rc::ptr_eq(parent, same_tree_parent)),
+                )
+            })?;
+        other_parents
+            .into_iter()
+            .all(|other| same_tree_parent.is_descendant_of(other) || Rc::ptr_eq(other, same_tree_parent))
+            .then_some(same_tree_parent)
+    }
+
+    /// Repeatedly applies [`Self::is_trivial_merge`] until `self` is not one,
+    /// collapsing a whole run of bump-carrying-nothing merges at once. Any
+    /// caller that picks a [`ThinCommit`] as an anchor for expansion
+    /// decisions (an injection tip, a regression check's old or new tip)
+    /// should collapse it through this first, the same way
+    /// [`TopRepo::expand_submodule_ref_onto_head`] already does for the
+    /// commit it injects, so those decisions are never made against a merge
+    /// that is about to be collapsed away anyway.
+    ///
+    /// PARTIAL: only [`TopRepo::expand_submodule_ref_onto_head`] collapses
+    /// through this today. A trivial merge surfacing mid-history during a
+    /// full refilter/expand is not collapsed, because that path lives in
+    /// `get_recursive_submodule_bumps`/`expand_inner_submodules` in the
+    /// expander, which this request does not touch.
+    pub fn collapse_trivial_merges(self: &Rc<Self>) -> Rc<ThinCommit> {
+        let mut current = self.clone();
+        while let Some(collapsed) = current.is_trivial_merge() {
+            current = collapsed.clone();
+        }
+        current
+    }
+
     /// Walks the first parent commit graph to the submodule entry.
     pub fn get_submodule<'a>(&'a self, path: &GitPath) -> Option<&'a ThinSubmodule> {
         let mut node = self;
@@ -1277,6 +3163,51 @@ impl ThinCommit {
         }
         None
     }
+
+    /// Names this commit relative to the nearest tag reachable through any
+    /// of its submodules, the way [`RepoData::describe`] names a single
+    /// submodule commit. Every submodule path touched by `self` or an
+    /// ancestor of it (i.e. every key in [`Self::submodule_paths`]) is
+    /// described independently and the closest match (smallest distance
+    /// from its tag) wins, with the winning path returned alongside the
+    /// description so callers can report which submodule it came from.
+    pub fn describe_via_submodules(&self, repos: &RepoStates) -> Option<ThinCommitDescribe> {
+        self.submodule_paths
+            .iter()
+            .filter_map(|path| {
+                let ThinSubmodule::AddedOrModified(content) = self.get_submodule(path)? else {
+                    return None;
+                };
+                let repo_data = repos.get(&RepoName::SubRepo(content.repo_name.clone()?))?;
+                let described = repo_data.describe_with_distance(content.commit_id)?;
+                Some(ThinCommitDescribe {
+                    submodule_path: path.clone(),
+                    description: described.description,
+                    distance: described.distance,
+                })
+            })
+            // Compare the distance `RepoData::describe_with_distance`
+            // already computed, rather than re-parsing it out of the
+            // rendered `<tag>-<N>-g<hash>` string: a tag name that itself
+            // ends in a numeric `-N` suffix makes that parse ambiguous,
+            // e.g. an exact match on tag `build-7-2` renders as
+            // `"build-7-2"` (distance 0), which a naive
+            // rsplit_once/rsplit_once parse misreads as tag `build`,
+            // distance 7.
+            .min_by_key(|candidate| candidate.distance)
+    }
+}
+
+/// The result of [`ThinCommit::describe_via_submodules`]: a `git
+/// describe`-style name for a submodule tip, together with the path of the
+/// submodule it was computed from and the raw distance (in commits) from
+/// the tag it was computed from, for callers that need to compare
+/// candidates without re-parsing `description`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThinCommitDescribe {
+    pub submodule_path: GitPath,
+    pub description: String,
+    pub distance: u32,
 }
 
 #[cfg(test)]
@@ -1354,4 +3285,189 @@ mod tests {
         }
         Ok(())
     }
+
+    fn test_commit_id(n: u8) -> CommitId {
+        gix::ObjectId::from_hex(format!("{n:040x}").as_bytes()).expect("valid hex commit id")
+    }
+
+    fn test_tree_id(n: u8) -> TreeId {
+        gix::ObjectId::from_hex(format!("{n:040x}").as_bytes()).expect("valid hex tree id")
+    }
+
+    #[test]
+    fn test_is_regression_detects_non_ancestor_and_not_descendant() {
+        let root = ThinCommit::new_rc(
+            test_commit_id(1),
+            test_tree_id(1),
+            vec![],
+            None,
+            BTreeMap::new(),
+        );
+        let forward = ThinCommit::new_rc(
+            test_commit_id(2),
+            test_tree_id(2),
+            vec![root.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        let unrelated = ThinCommit::new_rc(
+            test_commit_id(3),
+            test_tree_id(3),
+            vec![],
+            None,
+            BTreeMap::new(),
+        );
+
+        // Moving a submodule pointer forward to a descendant is not a regression...
+        assert!(!root.is_regression(&forward));
+        // ...but moving it back to an ancestor, or sideways to something
+        // unrelated, is.
+        assert!(forward.is_regression(&root));
+        assert!(root.is_regression(&unrelated));
+        // Staying put is never a regression.
+        assert!(!root.is_regression(&root));
+    }
+
+    #[test]
+    fn test_is_trivial_merge_requires_every_other_parent_to_be_an_ancestor() {
+        let base = ThinCommit::new_rc(
+            test_commit_id(1),
+            test_tree_id(1),
+            vec![],
+            None,
+            BTreeMap::new(),
+        );
+        let advanced = ThinCommit::new_rc(
+            test_commit_id(2),
+            test_tree_id(2),
+            vec![base.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        let unrelated = ThinCommit::new_rc(
+            test_commit_id(3),
+            test_tree_id(3),
+            vec![],
+            None,
+            BTreeMap::new(),
+        );
+
+        // A merge whose tree matches `advanced` and whose other parent
+        // (`base`) is an ancestor of `advanced` carries no bump of its own:
+        // it collapses onto `advanced`.
+        let trivial_merge = ThinCommit::new_rc(
+            test_commit_id(4),
+            test_tree_id(2),
+            vec![advanced.clone(), base.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        assert!(Rc::ptr_eq(
+            trivial_merge.is_trivial_merge().unwrap(),
+            &advanced
+        ));
+
+        // Same same-tree parent, but the other parent is unrelated rather
+        // than an ancestor: this merge genuinely brings in `unrelated`'s
+        // history, so it is not trivial.
+        let non_trivial_merge = ThinCommit::new_rc(
+            test_commit_id(5),
+            test_tree_id(2),
+            vec![advanced.clone(), unrelated.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        assert!(non_trivial_merge.is_trivial_merge().is_none());
+
+        // A merge is never trivial if no parent's tree matches its own.
+        let bump_merge = ThinCommit::new_rc(
+            test_commit_id(6),
+            test_tree_id(6),
+            vec![advanced.clone(), base.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        assert!(bump_merge.is_trivial_merge().is_none());
+
+        // A run of two trivial merges in a row collapses all the way down
+        // to the first commit that actually carries a bump.
+        let trivial_merge2 = ThinCommit::new_rc(
+            test_commit_id(7),
+            test_tree_id(2),
+            vec![trivial_merge.clone(), advanced.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        assert!(Rc::ptr_eq(&trivial_merge2.collapse_trivial_merges(), &advanced));
+    }
+
+    #[test]
+    fn test_submodule_expansion_guard_detects_cycle_and_depth_limit() {
+        let mut guard = SubmoduleExpansionGuard::new(Some(2));
+
+        guard.enter("https://example.com/sub.git", test_commit_id(1), 1).unwrap();
+        // Entering the exact same (url, commit_id) pair again while it is
+        // still on the active path is a self-inclusion cycle.
+        let err = guard
+            .enter("https://example.com/sub.git", test_commit_id(1), 2)
+            .unwrap_err();
+        assert!(err.to_string().contains("cycle"));
+
+        // The same commit id under a different URL is not a cycle: it is a
+        // coincidence, not a loop.
+        guard
+            .enter("https://example.com/other.git", test_commit_id(1), 2)
+            .unwrap();
+        guard.exit();
+        guard.exit();
+
+        // Once popped, re-entering the same pair at a fresh depth is fine.
+        guard.enter("https://example.com/sub.git", test_commit_id(1), 1).unwrap();
+        guard.exit();
+
+        // Depth beyond --max-submodule-depth is rejected even without a cycle.
+        let err = guard
+            .enter("https://example.com/sub.git", test_commit_id(2), 3)
+            .unwrap_err();
+        assert!(err.to_string().contains("max-submodule-depth"));
+    }
+
+    #[test]
+    fn test_describe_with_distance_exact_match_on_hyphenated_tag() {
+        let mut repo_data = RepoData::new(gix::url::Url::try_from("https://example.com/sub.git").unwrap());
+        let tagged = ThinCommit::new_rc(
+            test_commit_id(1),
+            test_tree_id(1),
+            vec![],
+            None,
+            BTreeMap::new(),
+        );
+        repo_data.thin_commits.insert(tagged.commit_id, tagged.clone());
+        // A tag name that itself ends in a numeric-looking "-N" suffix must
+        // not be misread as a "<tag>-<N>-g<hash>" distance suffix.
+        repo_data
+            .tags
+            .insert(tagged.commit_id, "build-7-2".to_string());
+
+        let described = repo_data.describe_with_distance(tagged.commit_id).unwrap();
+        assert_eq!(described.description, "build-7-2");
+        assert_eq!(described.distance, 0);
+
+        let descendant = ThinCommit::new_rc(
+            test_commit_id(2),
+            test_tree_id(2),
+            vec![tagged.clone()],
+            None,
+            BTreeMap::new(),
+        );
+        repo_data
+            .thin_commits
+            .insert(descendant.commit_id, descendant.clone());
+        let described = repo_data.describe_with_distance(descendant.commit_id).unwrap();
+        assert_eq!(described.distance, 1);
+        assert_eq!(
+            described.description,
+            format!("build-7-2-1-g{}", descendant.commit_id.to_hex_with_len(7))
+        );
+    }
 }